@@ -1,17 +1,23 @@
-use crate::token::Token;
+use crate::token::{Span, Token};
 use core::borrow::Borrow;
 
+// The nodes that carry a `span` are those the parser records where it found them; a
+// statement or expression has the span of the Statement or Expression around it. Nodes
+// built without a parser have no span.
 #[derive(Debug, Clone)]
 pub enum Node {
     Statement {
         node: Box<Node>,
+        span: Option<Span>,
     },
     Expression {
         node: Box<Node>,
+        span: Option<Span>,
     },
 
     Program {
         statements: Vec<Box<Node>>,
+        span: Option<Span>,
     },
 
     // Statement
@@ -24,6 +30,10 @@ pub enum Node {
         token: Token,
         return_value: Box<Node>,
     },
+    ThrowStatement {
+        token: Token,
+        value: Box<Node>,
+    },
     ExpressionStatement {
         token: Token,
         expression: Box<Node>,
@@ -31,12 +41,50 @@ pub enum Node {
     BlockStatement {
         token: Token,
         statements: Vec<Box<Node>>,
+        span: Option<Span>,
     },
+    FunctionDeclaration {
+        token: Token,
+        name: Box<Node>,
+        parameters: Vec<Box<Node>>,
+        body: Box<Node>,
+    }, // name is Identifier, hoisted to the top of its Program or BlockStatement
+    StructDeclaration {
+        token: Token,
+        name: Box<Node>,
+        fields: Vec<Box<Node>>,
+    }, // name and fields are Identifier
+    ImplDeclaration {
+        token: Token,
+        name: Box<Node>,
+        methods: Vec<Box<Node>>,
+    }, // name is Identifier, methods are FunctionDeclaration
+    ImportStatement {
+        token: Token,
+        path: Box<Node>,
+        name: Box<Node>,
+    }, // path is StringLiteral, name is Identifier
+    ExportStatement {
+        token: Token,
+        statement: Box<Node>,
+    }, // statement is LetStatement or FunctionDeclaration
+    EnumDeclaration {
+        token: Token,
+        name: Box<Node>,
+        variants: Vec<Box<Node>>,
+    }, // name is Identifier, variants are EnumVariant
+    EnumVariant {
+        token: Token,
+        name: Box<Node>,
+        fields: Vec<Box<Node>>,
+        span: Option<Span>,
+    }, // name and fields are Identifier
 
     // Expression
     Identifier {
         token: Token,
         value: String,
+        span: Option<Span>,
     },
     IntegerLiteral {
         token: Token,
@@ -54,6 +102,16 @@ pub enum Node {
         token: Token,
         elements: Vec<Box<Node>>,
     },
+    SetLiteral {
+        token: Token,
+        elements: Vec<Box<Node>>,
+    },
+    StructLiteral {
+        token: Token,
+        name: Box<Node>,
+        fields: Vec<Box<Node>>,
+        values: Vec<Box<Node>>,
+    }, // name and fields are Identifier, values are Expression
     PrefixExpression {
         token: Token,
         operator: String,
@@ -69,6 +127,9 @@ pub enum Node {
         token: Token,
         value: bool,
     },
+    NullLiteral {
+        token: Token,
+    },
     IfExpression {
         token: Token,
         condition: Box<Node>,
@@ -80,6 +141,19 @@ pub enum Node {
         parameters: Vec<Box<Node>>,
         body: Box<Node>,
     },
+    MatchExpression {
+        token: Token,
+        subject: Box<Node>,
+        patterns: Vec<Box<Node>>,
+        results: Vec<Box<Node>>,
+    }, // patterns are Expression read as patterns, results are Expression or BlockStatement
+    TryExpression {
+        token: Token,
+        block: Box<Node>,
+        parameter: Option<Box<Node>>,
+        handler: Option<Box<Node>>,
+        finalizer: Option<Box<Node>>,
+    }, // parameter is Identifier, block, handler and finalizer are BlockStatement
     CallExpression {
         token: Token,
         function: Box<Node>,
@@ -89,10 +163,51 @@ pub enum Node {
         token: Token,
         left: Box<Node>,
         index: Box<Node>,
-    },
+    }, // token is OptionalDot for `left?.[index]`
+    SliceExpression {
+        token: Token,
+        left: Box<Node>,
+        start: Option<Box<Node>>,
+        end: Option<Box<Node>>,
+    }, // `left[start:end]`, either bound may be left out
+    PropagateExpression {
+        token: Token,
+        left: Box<Node>,
+    }, // `left?` returns early from the enclosing function when left is an err
+    MemberExpression {
+        token: Token,
+        left: Box<Node>,
+        member: Box<Node>,
+    }, // member is Identifier, token is OptionalDot for `left?.member`
 }
 
 impl Node {
+    /// Where the parser found this node, if it is one that records its span.
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            Node::Statement { span, .. }
+            | Node::Expression { span, .. }
+            | Node::Program { span, .. }
+            | Node::BlockStatement { span, .. }
+            | Node::EnumVariant { span, .. }
+            | Node::Identifier { span, .. } => *span,
+            _ => None,
+        }
+    }
+
+    /// Records where this node was found; nodes that carry no span are left unchanged.
+    pub fn set_span(&mut self, new_span: Span) {
+        match self {
+            Node::Statement { span, .. }
+            | Node::Expression { span, .. }
+            | Node::Program { span, .. }
+            | Node::BlockStatement { span, .. }
+            | Node::EnumVariant { span, .. }
+            | Node::Identifier { span, .. } => *span = Some(new_span),
+            _ => {}
+        }
+    }
+
     pub fn new_statement(node: Box<Node>) -> Option<Box<Node>> {
         if Node::is_statement(node.borrow()) {
             return Some(node);
@@ -107,6 +222,7 @@ impl Node {
                 token: _,
                 return_value: _,
             } => Some(node),
+            &Node::ThrowStatement { token: _, value: _ } => Some(node),
             &Node::ExpressionStatement {
                 token: _,
                 expression: _,
@@ -114,23 +230,64 @@ impl Node {
             &Node::BlockStatement {
                 token: _,
                 statements: _,
+                span: _,
+            } => Some(node),
+            &Node::FunctionDeclaration {
+                token: _,
+                name: _,
+                parameters: _,
+                body: _,
+            } => Some(node),
+            &Node::StructDeclaration {
+                token: _,
+                name: _,
+                fields: _,
+            } => Some(node),
+            &Node::EnumDeclaration {
+                token: _,
+                name: _,
+                variants: _,
+            } => Some(node),
+            &Node::ImplDeclaration {
+                token: _,
+                name: _,
+                methods: _,
+            } => Some(node),
+            &Node::ImportStatement {
+                token: _,
+                path: _,
+                name: _,
+            } => Some(node),
+            &Node::ExportStatement {
+                token: _,
+                statement: _,
             } => Some(node),
             _ => None,
         };
-        return node.and_then(|node| Some(Box::new(Node::Statement { node })));
+        return node.and_then(|node| Some(Box::new(Node::Statement { node, span: None })));
     }
 
     pub fn new_expression(node: Box<Node>) -> Option<Box<Node>> {
-        eprintln!("[new_expression 1] node is {:?}", node);
         if Node::is_expression(node.borrow()) {
             return Some(node);
         }
         let node = match node.borrow() {
-            &Node::Identifier { token: _, value: _ } => Some(node),
+            &Node::Identifier {
+                token: _,
+                value: _,
+                span: _,
+            } => Some(node),
             &Node::IntegerLiteral { token: _, value: _ } => Some(node),
             &Node::StringLiteral { token: _, value: _ } => Some(node),
             &Node::ArrayLiteral { token: _, elements: _ } => Some(node),
+            &Node::SetLiteral { token: _, elements: _ } => Some(node),
             &Node::HashLiteral { token: _, elements: _ } => Some(node),
+            &Node::StructLiteral {
+                token: _,
+                name: _,
+                fields: _,
+                values: _,
+            } => Some(node),
             &Node::PrefixExpression {
                 token: _,
                 operator: _,
@@ -143,6 +300,7 @@ impl Node {
                 right: _,
             } => Some(node),
             &Node::Boolean { token: _, value: _ } => Some(node),
+            &Node::NullLiteral { token: _ } => Some(node),
             &Node::IfExpression {
                 token: _,
                 condition: _,
@@ -154,6 +312,19 @@ impl Node {
                 parameters: _,
                 body: _,
             } => Some(node),
+            &Node::MatchExpression {
+                token: _,
+                subject: _,
+                patterns: _,
+                results: _,
+            } => Some(node),
+            &Node::TryExpression {
+                token: _,
+                block: _,
+                parameter: _,
+                handler: _,
+                finalizer: _,
+            } => Some(node),
             &Node::CallExpression {
                 token: _,
                 function: _,
@@ -164,11 +335,22 @@ impl Node {
                 left: _,
                 index: _,
             } => Some(node),
-            &Node::Expression { node: _ } => Some(node),
+            &Node::SliceExpression {
+                token: _,
+                left: _,
+                start: _,
+                end: _,
+            } => Some(node),
+            &Node::MemberExpression {
+                token: _,
+                left: _,
+                member: _,
+            } => Some(node),
+            &Node::PropagateExpression { token: _, left: _ } => Some(node),
+            &Node::Expression { node: _, span: _ } => Some(node),
             _ => None,
         };
-        eprintln!("[new_expression 2] node is {:?}", node);
-        return node.and_then(|node| Some(Box::new(Node::Expression { node })));
+        return node.and_then(|node| Some(Box::new(Node::Expression { node, span: None })));
     }
 
     pub fn new_program(statements: Vec<Box<Node>>) -> Option<Box<Node>> {
@@ -178,7 +360,10 @@ impl Node {
             }
         }
 
-        return Some(Box::new(Node::Program { statements }));
+        return Some(Box::new(Node::Program {
+            statements,
+            span: None,
+        }));
     }
 
     pub fn new_let_statement(token: Token, name: Box<Node>, value: Box<Node>) -> Option<Box<Node>> {
@@ -204,9 +389,16 @@ impl Node {
         }));
     }
 
+    pub fn new_throw_statement(token: Token, value: Box<Node>) -> Option<Box<Node>> {
+        if !Node::is_expression(value.borrow()) {
+            return None;
+        }
+
+        return Some(Box::new(Node::ThrowStatement { token, value }));
+    }
+
     pub fn new_expression_statement(token: Token, expression: Box<Node>) -> Option<Box<Node>> {
         if !Node::is_expression(expression.borrow()) {
-            eprintln!("{:?} is not expression", expression);
             return None;
         }
 
@@ -220,11 +412,162 @@ impl Node {
             }
         }
 
-        return Some(Box::new(Node::BlockStatement { token, statements }));
+        return Some(Box::new(Node::BlockStatement {
+            token,
+            statements,
+            span: None,
+        }));
+    }
+
+    pub fn new_function_declaration(
+        token: Token,
+        name: Box<Node>,
+        parameters: Vec<Box<Node>>,
+        body: Box<Node>,
+    ) -> Option<Box<Node>> {
+        if !Node::is_identifier(name.borrow()) {
+            return None;
+        }
+
+        for p in parameters.iter() {
+            if !Node::is_identifier(p.borrow()) {
+                return None;
+            }
+        }
+
+        if !Node::is_block_statement(body.borrow()) {
+            return None;
+        }
+
+        Some(Box::new(Node::FunctionDeclaration {
+            token,
+            name,
+            parameters,
+            body,
+        }))
+    }
+
+    pub fn new_struct_declaration(
+        token: Token,
+        name: Box<Node>,
+        fields: Vec<Box<Node>>,
+    ) -> Option<Box<Node>> {
+        if !Node::is_identifier(name.borrow()) {
+            return None;
+        }
+
+        for f in fields.iter() {
+            if !Node::is_identifier(f.borrow()) {
+                return None;
+            }
+        }
+
+        Some(Box::new(Node::StructDeclaration {
+            token,
+            name,
+            fields,
+        }))
+    }
+
+    pub fn new_impl_declaration(
+        token: Token,
+        name: Box<Node>,
+        methods: Vec<Box<Node>>,
+    ) -> Option<Box<Node>> {
+        if !Node::is_identifier(name.borrow()) {
+            return None;
+        }
+
+        for m in methods.iter() {
+            if !Node::is_function_declaration(m.borrow()) {
+                return None;
+            }
+        }
+
+        Some(Box::new(Node::ImplDeclaration {
+            token,
+            name,
+            methods,
+        }))
+    }
+
+    pub fn new_import_statement(
+        token: Token,
+        path: Box<Node>,
+        name: Box<Node>,
+    ) -> Option<Box<Node>> {
+        if !Node::is_string_literal(path.borrow()) || !Node::is_identifier(name.borrow()) {
+            return None;
+        }
+
+        Some(Box::new(Node::ImportStatement { token, path, name }))
+    }
+
+    pub fn new_export_statement(token: Token, statement: Box<Node>) -> Option<Box<Node>> {
+        match statement.borrow() {
+            &Node::LetStatement {
+                token: _,
+                name: _,
+                value: _,
+            } => {}
+            _ if Node::is_function_declaration(statement.borrow()) => {}
+            _ => return None,
+        }
+
+        Some(Box::new(Node::ExportStatement { token, statement }))
+    }
+
+    pub fn new_enum_declaration(
+        token: Token,
+        name: Box<Node>,
+        variants: Vec<Box<Node>>,
+    ) -> Option<Box<Node>> {
+        if !Node::is_identifier(name.borrow()) {
+            return None;
+        }
+
+        for v in variants.iter() {
+            if !Node::is_enum_variant(v.borrow()) {
+                return None;
+            }
+        }
+
+        Some(Box::new(Node::EnumDeclaration {
+            token,
+            name,
+            variants,
+        }))
+    }
+
+    pub fn new_enum_variant(
+        token: Token,
+        name: Box<Node>,
+        fields: Vec<Box<Node>>,
+    ) -> Option<Box<Node>> {
+        if !Node::is_identifier(name.borrow()) {
+            return None;
+        }
+
+        for f in fields.iter() {
+            if !Node::is_identifier(f.borrow()) {
+                return None;
+            }
+        }
+
+        Some(Box::new(Node::EnumVariant {
+            token,
+            name,
+            fields,
+            span: None,
+        }))
     }
 
     pub fn new_identifier(token: Token, value: String) -> Box<Node> {
-        Box::new(Node::Identifier { token, value })
+        Box::new(Node::Identifier {
+            token,
+            value,
+            span: None,
+        })
     }
 
     pub fn new_integer_literal(token: Token, value: i64) -> Box<Node> {
@@ -251,6 +594,19 @@ impl Node {
         }))
     }
 
+    pub fn new_set_literal(
+        token: Token,
+        elements: Vec<Box<Node>>,
+    ) -> Option<Box<Node>> {
+        for e in elements.iter() {
+            if !Node::is_expression(e.borrow()) {
+                return None;
+            }
+        }
+
+        Some(Box::new(Node::SetLiteral { token, elements }))
+    }
+
     pub fn new_hash_literal(
         token: Token,
         elements: Vec<Box<Node>>,
@@ -267,6 +623,40 @@ impl Node {
         }))
     }
 
+    pub fn new_struct_literal(
+        token: Token,
+        name: Box<Node>,
+        fields: Vec<Box<Node>>,
+        values: Vec<Box<Node>>,
+    ) -> Option<Box<Node>> {
+        if !Node::is_identifier(name.borrow()) {
+            return None;
+        }
+
+        if fields.len() != values.len() {
+            return None;
+        }
+
+        for f in fields.iter() {
+            if !Node::is_identifier(f.borrow()) {
+                return None;
+            }
+        }
+
+        for v in values.iter() {
+            if !Node::is_expression(v.borrow()) {
+                return None;
+            }
+        }
+
+        Some(Box::new(Node::StructLiteral {
+            token,
+            name,
+            fields,
+            values,
+        }))
+    }
+
     pub fn new_prefix_expression(
         token: Token,
         operator: String,
@@ -290,12 +680,10 @@ impl Node {
         right: Box<Node>,
     ) -> Option<Box<Node>> {
         if !Node::is_expression(left.borrow()) {
-            eprintln!("left is not expression. {:?}", left);
             return None;
         }
 
         if !Node::is_expression(right.borrow()) {
-            eprintln!("right is not expression. {:?}", right);
             return None;
         }
 
@@ -311,6 +699,10 @@ impl Node {
         Box::new(Node::Boolean { token, value })
     }
 
+    pub fn new_null_literal(token: Token) -> Box<Node> {
+        Box::new(Node::NullLiteral { token })
+    }
+
     pub fn new_if_expression(
         token: Token,
         condition: Box<Node>,
@@ -361,6 +753,77 @@ impl Node {
         }))
     }
 
+    pub fn new_match_expression(
+        token: Token,
+        subject: Box<Node>,
+        patterns: Vec<Box<Node>>,
+        results: Vec<Box<Node>>,
+    ) -> Option<Box<Node>> {
+        if !Node::is_expression(subject.borrow()) {
+            return None;
+        }
+
+        if patterns.len() != results.len() {
+            return None;
+        }
+
+        for p in patterns.iter() {
+            if !Node::is_expression(p.borrow()) {
+                return None;
+            }
+        }
+
+        for r in results.iter() {
+            if !Node::is_expression(r.borrow()) && !Node::is_block_statement(r.borrow()) {
+                return None;
+            }
+        }
+
+        Some(Box::new(Node::MatchExpression {
+            token,
+            subject,
+            patterns,
+            results,
+        }))
+    }
+
+    pub fn new_try_expression(
+        token: Token,
+        block: Box<Node>,
+        parameter: Option<Box<Node>>,
+        handler: Option<Box<Node>>,
+        finalizer: Option<Box<Node>>,
+    ) -> Option<Box<Node>> {
+        if !Node::is_block_statement(block.borrow()) {
+            return None;
+        }
+
+        if handler.is_none() && finalizer.is_none() {
+            return None;
+        }
+
+        if parameter.is_some() && !Node::is_identifier(parameter.as_ref().unwrap().borrow()) {
+            return None;
+        }
+
+        if handler.is_some() && !Node::is_block_statement(handler.as_ref().unwrap().borrow()) {
+            return None;
+        }
+
+        if finalizer.is_some() && !Node::is_block_statement(finalizer.as_ref().unwrap().borrow())
+        {
+            return None;
+        }
+
+        Some(Box::new(Node::TryExpression {
+            token,
+            block,
+            parameter,
+            handler,
+            finalizer,
+        }))
+    }
+
     pub fn new_call_expression(
         token: Token,
         function: Box<Node>,
@@ -403,23 +866,110 @@ impl Node {
         }))
     }
 
+    pub fn new_slice_expression(
+        token: Token,
+        left: Box<Node>,
+        start: Option<Box<Node>>,
+        end: Option<Box<Node>>,
+    ) -> Option<Box<Node>> {
+        if !Node::is_expression(left.borrow()) {
+            return None;
+        }
+
+        for bound in start.iter().chain(end.iter()) {
+            if !Node::is_expression(bound.borrow()) {
+                return None;
+            }
+        }
+
+        Some(Box::new(Node::SliceExpression {
+            token,
+            left,
+            start,
+            end,
+        }))
+    }
+
+    pub fn new_propagate_expression(token: Token, left: Box<Node>) -> Option<Box<Node>> {
+        if !Node::is_expression(left.borrow()) {
+            return None;
+        }
+
+        Some(Box::new(Node::PropagateExpression { token, left }))
+    }
+
+    pub fn new_member_expression(
+        token: Token,
+        left: Box<Node>,
+        member: Box<Node>,
+    ) -> Option<Box<Node>> {
+        if !Node::is_expression(left.borrow()) {
+            return None;
+        }
+
+        if !Node::is_identifier(member.borrow()) {
+            return None;
+        }
+
+        Some(Box::new(Node::MemberExpression {
+            token,
+            left,
+            member,
+        }))
+    }
+
     fn is_statement(node: &Node) -> bool {
         match node {
-            &Node::Statement { node: _ } => true,
+            &Node::Statement { node: _, span: _ } => true,
             _ => false,
         }
     }
 
     fn is_expression(node: &Node) -> bool {
         match node {
-            &Node::Expression { node: _ } => true,
+            &Node::Expression { node: _, span: _ } => true,
             _ => false,
         }
     }
 
     fn is_identifier(node: &Node) -> bool {
         match node {
-            &Node::Identifier { token: _, value: _ } => true,
+            &Node::Identifier {
+                token: _,
+                value: _,
+                span: _,
+            } => true,
+            _ => false,
+        }
+    }
+
+    fn is_string_literal(node: &Node) -> bool {
+        match node {
+            &Node::StringLiteral { token: _, value: _ } => true,
+            _ => false,
+        }
+    }
+
+    fn is_function_declaration(node: &Node) -> bool {
+        match node {
+            &Node::FunctionDeclaration {
+                token: _,
+                name: _,
+                parameters: _,
+                body: _,
+            } => true,
+            _ => false,
+        }
+    }
+
+    fn is_enum_variant(node: &Node) -> bool {
+        match node {
+            &Node::EnumVariant {
+                token: _,
+                name: _,
+                fields: _,
+                span: _,
+            } => true,
             _ => false,
         }
     }
@@ -429,6 +979,7 @@ impl Node {
             &Node::BlockStatement {
                 token: _,
                 statements: _,
+                span: _,
             } => true,
             _ => false,
         }
@@ -441,11 +992,11 @@ fn test_new_let_statement() {
     let ii = Node::new_identifier(Token::Ident("".to_string()), "".to_string());
     let ii = Node::new_expression(ii);
     let l = Node::new_let_statement(Token::Let, i, ii.unwrap());
-    println!("{:?}", l);
+    assert!(l.is_some());
 }
 
 #[test]
 fn test_is_identifier() {
     let i = Node::new_identifier(Token::Ident("".to_string()), "".to_string());
-    println!("{:?}", Node::is_identifier(i.borrow()));
+    assert!(Node::is_identifier(i.borrow()));
 }
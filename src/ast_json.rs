@@ -0,0 +1,603 @@
+use crate::ast::Node;
+use crate::json::Json;
+use crate::token::{Position, Span, Token};
+
+// Every node is an object whose `kind` names its variant, followed by its `span` when the
+// parser recorded one (a statement or expression has the span of its wrapper), its `token`, and then its fields in declaration order. Child nodes
+// are objects, lists of children are arrays and missing optional children are null.
+// Tokens without a value are their variant name, the others a one-member object such as
+// `{"Ident": "x"}`.
+
+/// The JSON form of `node`, including the spans the parser recorded when `spans` is set.
+pub fn to_json(node: &Node, spans: bool) -> Json {
+    let writer = Writer { spans };
+    writer.node(node)
+}
+
+/// Rebuilds a node from the JSON `to_json` produces, checking it with the same
+/// constructors the parser uses. Spans are kept on the nodes that carry one.
+pub fn from_json(json: &Json) -> Result<Box<Node>, String> {
+    let kind = match json.get("kind") {
+        Some(Json::String(kind)) => kind.as_str(),
+        _ => return Err("node must be an object with a string \"kind\"".to_owned()),
+    };
+    let reader = Reader { json, kind };
+    reader.node()
+}
+
+struct Writer {
+    spans: bool,
+}
+
+impl Writer {
+    fn node(&self, node: &Node) -> Json {
+        self.node_within(node, None)
+    }
+
+    // `outer` is the span of the Statement or Expression around `node`.
+    fn node_within(&self, node: &Node, outer: Option<Span>) -> Json {
+        let mut members = vec![];
+        let mut field = |name: &str, value: Json| members.push((name.to_owned(), value));
+
+        let kind = kind(node);
+        field("kind", Json::String(kind.to_owned()));
+        let span = node.span().or(outer);
+        if let Some(span) = span.filter(|_| self.spans) {
+            field("span", span_to_json(span));
+        }
+        if let Some(token) = token(node) {
+            field("token", token_to_json(token));
+        }
+
+        match node {
+            Node::Statement { node, span } | Node::Expression { node, span } => {
+                field("node", self.node_within(node, *span))
+            }
+            Node::Program { statements, .. } => field("statements", self.nodes(statements)),
+            Node::LetStatement { name, value, .. } => {
+                field("name", self.node(name));
+                field("value", self.node(value));
+            }
+            Node::ReturnStatement { return_value, .. } => {
+                field("return_value", self.node(return_value))
+            }
+            Node::ThrowStatement { value, .. } => field("value", self.node(value)),
+            Node::ExpressionStatement { expression, .. } => {
+                field("expression", self.node(expression))
+            }
+            Node::BlockStatement { statements, .. } => field("statements", self.nodes(statements)),
+            Node::FunctionDeclaration {
+                name,
+                parameters,
+                body,
+                ..
+            } => {
+                field("name", self.node(name));
+                field("parameters", self.nodes(parameters));
+                field("body", self.node(body));
+            }
+            Node::StructDeclaration { name, fields, .. }
+            | Node::EnumVariant { name, fields, .. } => {
+                field("name", self.node(name));
+                field("fields", self.nodes(fields));
+            }
+            Node::ImplDeclaration { name, methods, .. } => {
+                field("name", self.node(name));
+                field("methods", self.nodes(methods));
+            }
+            Node::ImportStatement { path, name, .. } => {
+                field("path", self.node(path));
+                field("name", self.node(name));
+            }
+            Node::ExportStatement { statement, .. } => field("statement", self.node(statement)),
+            Node::EnumDeclaration { name, variants, .. } => {
+                field("name", self.node(name));
+                field("variants", self.nodes(variants));
+            }
+            Node::Identifier { value, .. } | Node::StringLiteral { value, .. } => {
+                field("value", Json::String(value.to_owned()))
+            }
+            Node::IntegerLiteral { value, .. } => field("value", Json::Number(*value)),
+            Node::Boolean { value, .. } => field("value", Json::Bool(*value)),
+            Node::NullLiteral { .. } => {}
+            Node::ArrayLiteral { elements, .. }
+            | Node::HashLiteral { elements, .. }
+            | Node::SetLiteral { elements, .. } => field("elements", self.nodes(elements)),
+            Node::StructLiteral {
+                name,
+                fields,
+                values,
+                ..
+            } => {
+                field("name", self.node(name));
+                field("fields", self.nodes(fields));
+                field("values", self.nodes(values));
+            }
+            Node::PrefixExpression {
+                operator, right, ..
+            } => {
+                field("operator", Json::String(operator.to_owned()));
+                field("right", self.node(right));
+            }
+            Node::InfixExpression {
+                left,
+                operator,
+                right,
+                ..
+            } => {
+                field("left", self.node(left));
+                field("operator", Json::String(operator.to_owned()));
+                field("right", self.node(right));
+            }
+            Node::IfExpression {
+                condition,
+                consequence,
+                alternative,
+                ..
+            } => {
+                field("condition", self.node(condition));
+                field("consequence", self.node(consequence));
+                field("alternative", self.optional(alternative));
+            }
+            Node::FunctionLiteral {
+                parameters, body, ..
+            } => {
+                field("parameters", self.nodes(parameters));
+                field("body", self.node(body));
+            }
+            Node::MatchExpression {
+                subject,
+                patterns,
+                results,
+                ..
+            } => {
+                field("subject", self.node(subject));
+                field("patterns", self.nodes(patterns));
+                field("results", self.nodes(results));
+            }
+            Node::TryExpression {
+                block,
+                parameter,
+                handler,
+                finalizer,
+                ..
+            } => {
+                field("block", self.node(block));
+                field("parameter", self.optional(parameter));
+                field("handler", self.optional(handler));
+                field("finalizer", self.optional(finalizer));
+            }
+            Node::CallExpression {
+                function,
+                arguments,
+                ..
+            } => {
+                field("function", self.node(function));
+                field("arguments", self.nodes(arguments));
+            }
+            Node::IndexExpression { left, index, .. } => {
+                field("left", self.node(left));
+                field("index", self.node(index));
+            }
+            Node::SliceExpression {
+                left, start, end, ..
+            } => {
+                field("left", self.node(left));
+                field("start", self.optional(start));
+                field("end", self.optional(end));
+            }
+            Node::PropagateExpression { left, .. } => field("left", self.node(left)),
+            Node::MemberExpression { left, member, .. } => {
+                field("left", self.node(left));
+                field("member", self.node(member));
+            }
+        }
+        Json::Object(members)
+    }
+
+    fn nodes(&self, nodes: &[Box<Node>]) -> Json {
+        Json::Array(nodes.iter().map(|node| self.node(node)).collect())
+    }
+
+    fn optional(&self, node: &Option<Box<Node>>) -> Json {
+        match node {
+            Some(node) => self.node(node),
+            None => Json::Null,
+        }
+    }
+}
+
+fn kind(node: &Node) -> &'static str {
+    match node {
+        Node::Statement { .. } => "Statement",
+        Node::Expression { .. } => "Expression",
+        Node::Program { .. } => "Program",
+        Node::LetStatement { .. } => "LetStatement",
+        Node::ReturnStatement { .. } => "ReturnStatement",
+        Node::ThrowStatement { .. } => "ThrowStatement",
+        Node::ExpressionStatement { .. } => "ExpressionStatement",
+        Node::BlockStatement { .. } => "BlockStatement",
+        Node::FunctionDeclaration { .. } => "FunctionDeclaration",
+        Node::StructDeclaration { .. } => "StructDeclaration",
+        Node::ImplDeclaration { .. } => "ImplDeclaration",
+        Node::ImportStatement { .. } => "ImportStatement",
+        Node::ExportStatement { .. } => "ExportStatement",
+        Node::EnumDeclaration { .. } => "EnumDeclaration",
+        Node::EnumVariant { .. } => "EnumVariant",
+        Node::Identifier { .. } => "Identifier",
+        Node::IntegerLiteral { .. } => "IntegerLiteral",
+        Node::StringLiteral { .. } => "StringLiteral",
+        Node::ArrayLiteral { .. } => "ArrayLiteral",
+        Node::HashLiteral { .. } => "HashLiteral",
+        Node::SetLiteral { .. } => "SetLiteral",
+        Node::StructLiteral { .. } => "StructLiteral",
+        Node::PrefixExpression { .. } => "PrefixExpression",
+        Node::InfixExpression { .. } => "InfixExpression",
+        Node::Boolean { .. } => "Boolean",
+        Node::NullLiteral { .. } => "NullLiteral",
+        Node::IfExpression { .. } => "IfExpression",
+        Node::FunctionLiteral { .. } => "FunctionLiteral",
+        Node::MatchExpression { .. } => "MatchExpression",
+        Node::TryExpression { .. } => "TryExpression",
+        Node::CallExpression { .. } => "CallExpression",
+        Node::IndexExpression { .. } => "IndexExpression",
+        Node::SliceExpression { .. } => "SliceExpression",
+        Node::PropagateExpression { .. } => "PropagateExpression",
+        Node::MemberExpression { .. } => "MemberExpression",
+    }
+}
+
+fn token(node: &Node) -> Option<&Token> {
+    match node {
+        Node::Statement { .. } | Node::Expression { .. } | Node::Program { .. } => None,
+        Node::LetStatement { token, .. }
+        | Node::ReturnStatement { token, .. }
+        | Node::ThrowStatement { token, .. }
+        | Node::ExpressionStatement { token, .. }
+        | Node::BlockStatement { token, .. }
+        | Node::FunctionDeclaration { token, .. }
+        | Node::StructDeclaration { token, .. }
+        | Node::ImplDeclaration { token, .. }
+        | Node::ImportStatement { token, .. }
+        | Node::ExportStatement { token, .. }
+        | Node::EnumDeclaration { token, .. }
+        | Node::EnumVariant { token, .. }
+        | Node::Identifier { token, .. }
+        | Node::IntegerLiteral { token, .. }
+        | Node::StringLiteral { token, .. }
+        | Node::ArrayLiteral { token, .. }
+        | Node::HashLiteral { token, .. }
+        | Node::SetLiteral { token, .. }
+        | Node::StructLiteral { token, .. }
+        | Node::PrefixExpression { token, .. }
+        | Node::InfixExpression { token, .. }
+        | Node::Boolean { token, .. }
+        | Node::NullLiteral { token }
+        | Node::IfExpression { token, .. }
+        | Node::FunctionLiteral { token, .. }
+        | Node::MatchExpression { token, .. }
+        | Node::TryExpression { token, .. }
+        | Node::CallExpression { token, .. }
+        | Node::IndexExpression { token, .. }
+        | Node::SliceExpression { token, .. }
+        | Node::PropagateExpression { token, .. }
+        | Node::MemberExpression { token, .. } => Some(token),
+    }
+}
+
+fn span_to_json(span: Span) -> Json {
+    let position = |p: Position| {
+        Json::Object(vec![
+            ("line".to_owned(), Json::Number(p.line as i64)),
+            ("column".to_owned(), Json::Number(p.column as i64)),
+            ("offset".to_owned(), Json::Number(p.offset as i64)),
+        ])
+    };
+    Json::Object(vec![
+        ("start".to_owned(), position(span.start)),
+        ("end".to_owned(), position(span.end)),
+    ])
+}
+
+fn span_from_json(json: &Json) -> Result<Span, String> {
+    Ok(Span {
+        start: position_from_json(json, "start")?,
+        end: position_from_json(json, "end")?,
+    })
+}
+
+fn position_from_json(span: &Json, name: &str) -> Result<Position, String> {
+    let json = span
+        .get(name)
+        .ok_or_else(|| format!("span must have a {:?} position", name))?;
+    let number = |field: &str| match json.get(field) {
+        Some(Json::Number(n)) if *n >= 0 => Ok(*n as usize),
+        _ => Err(format!("span position must have a number {:?}", field)),
+    };
+    Ok(Position {
+        line: number("line")?,
+        column: number("column")?,
+        offset: number("offset")?,
+    })
+}
+
+// The tokens that carry no value, by variant name.
+const SIMPLE_TOKENS: &[(&str, Token)] = &[
+    ("Eof", Token::Eof),
+    ("Assign", Token::Assign),
+    ("Plus", Token::Plus),
+    ("Minus", Token::Minus),
+    ("Bang", Token::Bang),
+    ("Asterisk", Token::Asterisk),
+    ("Slash", Token::Slash),
+    ("LT", Token::LT),
+    ("GT", Token::GT),
+    ("Comma", Token::Comma),
+    ("Colon", Token::Colon),
+    ("Dot", Token::Dot),
+    ("SemiColon", Token::SemiColon),
+    ("LParen", Token::LParen),
+    ("RParen", Token::RParen),
+    ("LBrace", Token::LBrace),
+    ("RBrace", Token::RBrace),
+    ("LBracket", Token::LBracket),
+    ("SetBrace", Token::SetBrace),
+    ("RBracket", Token::RBracket),
+    ("Eq", Token::Eq),
+    ("NotEq", Token::NotEq),
+    ("Pipe", Token::Pipe),
+    ("FatArrow", Token::FatArrow),
+    ("NullCoalesce", Token::NullCoalesce),
+    ("Question", Token::Question),
+    ("OptionalDot", Token::OptionalDot),
+    ("Function", Token::Function),
+    ("Let", Token::Let),
+    ("True", Token::True),
+    ("False", Token::False),
+    ("If", Token::If),
+    ("Else", Token::Else),
+    ("Return", Token::Return),
+    ("Null", Token::Null),
+    ("Try", Token::Try),
+    ("Catch", Token::Catch),
+    ("Finally", Token::Finally),
+    ("Throw", Token::Throw),
+    ("Struct", Token::Struct),
+    ("Enum", Token::Enum),
+    ("Match", Token::Match),
+    ("Impl", Token::Impl),
+    ("Import", Token::Import),
+    ("Export", Token::Export),
+    ("As", Token::As),
+];
+
+fn token_to_json(token: &Token) -> Json {
+    let tagged = |tag: &str, value: &str| {
+        Json::Object(vec![(tag.to_owned(), Json::String(value.to_owned()))])
+    };
+    match token {
+        Token::Illegal(s) => tagged("Illegal", s),
+        Token::Ident(s) => tagged("Ident", s),
+        Token::Int(s) => tagged("Int", s),
+        Token::Str(s) => tagged("Str", s),
+        token => {
+            let (name, _) = SIMPLE_TOKENS.iter().find(|(_, t)| t == token).unwrap();
+            Json::String((*name).to_owned())
+        }
+    }
+}
+
+fn token_from_json(json: &Json) -> Result<Token, String> {
+    match json {
+        Json::String(name) => SIMPLE_TOKENS
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, token)| token.clone())
+            .ok_or_else(|| format!("unknown token: {}", name)),
+        Json::Object(members) if members.len() == 1 => match &members[0] {
+            (tag, Json::String(s)) if tag == "Illegal" => Ok(Token::Illegal(s.to_owned())),
+            (tag, Json::String(s)) if tag == "Ident" => Ok(Token::Ident(s.to_owned())),
+            (tag, Json::String(s)) if tag == "Int" => Ok(Token::Int(s.to_owned())),
+            (tag, Json::String(s)) if tag == "Str" => Ok(Token::Str(s.to_owned())),
+            (tag, _) => Err(format!("unknown token: {}", tag)),
+        },
+        json => Err(format!(
+            "token must be a string or an object, got {}",
+            json.stringify(None)
+        )),
+    }
+}
+
+struct Reader<'a> {
+    json: &'a Json,
+    kind: &'a str,
+}
+
+impl<'a> Reader<'a> {
+    fn node(&self) -> Result<Box<Node>, String> {
+        let node = match self.kind {
+            "Statement" => Node::new_statement(self.child("node")?),
+            "Expression" => Node::new_expression(self.child("node")?),
+            "Program" => Node::new_program(self.children("statements")?),
+            "LetStatement" => {
+                Node::new_let_statement(self.token()?, self.child("name")?, self.child("value")?)
+            }
+            "ReturnStatement" => {
+                Node::new_return_statement(self.token()?, self.child("return_value")?)
+            }
+            "ThrowStatement" => Node::new_throw_statement(self.token()?, self.child("value")?),
+            "ExpressionStatement" => {
+                Node::new_expression_statement(self.token()?, self.child("expression")?)
+            }
+            "BlockStatement" => {
+                Node::new_block_statement(self.token()?, self.children("statements")?)
+            }
+            "FunctionDeclaration" => Node::new_function_declaration(
+                self.token()?,
+                self.child("name")?,
+                self.children("parameters")?,
+                self.child("body")?,
+            ),
+            "StructDeclaration" => Node::new_struct_declaration(
+                self.token()?,
+                self.child("name")?,
+                self.children("fields")?,
+            ),
+            "ImplDeclaration" => Node::new_impl_declaration(
+                self.token()?,
+                self.child("name")?,
+                self.children("methods")?,
+            ),
+            "ImportStatement" => {
+                Node::new_import_statement(self.token()?, self.child("path")?, self.child("name")?)
+            }
+            "ExportStatement" => {
+                Node::new_export_statement(self.token()?, self.child("statement")?)
+            }
+            "EnumDeclaration" => Node::new_enum_declaration(
+                self.token()?,
+                self.child("name")?,
+                self.children("variants")?,
+            ),
+            "EnumVariant" => {
+                Node::new_enum_variant(self.token()?, self.child("name")?, self.children("fields")?)
+            }
+            "Identifier" => Some(Node::new_identifier(self.token()?, self.string("value")?)),
+            "IntegerLiteral" => Some(Node::new_integer_literal(
+                self.token()?,
+                self.integer("value")?,
+            )),
+            "StringLiteral" => Some(Node::new_string_literal(
+                self.token()?,
+                self.string("value")?,
+            )),
+            "ArrayLiteral" => Node::new_array_literal(self.token()?, self.children("elements")?),
+            "HashLiteral" => Node::new_hash_literal(self.token()?, self.children("elements")?),
+            "SetLiteral" => Node::new_set_literal(self.token()?, self.children("elements")?),
+            "StructLiteral" => Node::new_struct_literal(
+                self.token()?,
+                self.child("name")?,
+                self.children("fields")?,
+                self.children("values")?,
+            ),
+            "PrefixExpression" => Node::new_prefix_expression(
+                self.token()?,
+                self.string("operator")?,
+                self.child("right")?,
+            ),
+            "InfixExpression" => Node::new_infix_expression(
+                self.token()?,
+                self.child("left")?,
+                self.string("operator")?,
+                self.child("right")?,
+            ),
+            "Boolean" => Some(Node::new_boolean(self.token()?, self.boolean("value")?)),
+            "NullLiteral" => Some(Node::new_null_literal(self.token()?)),
+            "IfExpression" => Node::new_if_expression(
+                self.token()?,
+                self.child("condition")?,
+                self.child("consequence")?,
+                self.optional("alternative")?,
+            ),
+            "FunctionLiteral" => Node::new_function_literal(
+                self.token()?,
+                self.children("parameters")?,
+                self.child("body")?,
+            ),
+            "MatchExpression" => Node::new_match_expression(
+                self.token()?,
+                self.child("subject")?,
+                self.children("patterns")?,
+                self.children("results")?,
+            ),
+            "TryExpression" => Node::new_try_expression(
+                self.token()?,
+                self.child("block")?,
+                self.optional("parameter")?,
+                self.optional("handler")?,
+                self.optional("finalizer")?,
+            ),
+            "CallExpression" => Node::new_call_expression(
+                self.token()?,
+                self.child("function")?,
+                self.children("arguments")?,
+            ),
+            "IndexExpression" => {
+                Node::new_index_expression(self.token()?, self.child("left")?, self.child("index")?)
+            }
+            "SliceExpression" => Node::new_slice_expression(
+                self.token()?,
+                self.child("left")?,
+                self.optional("start")?,
+                self.optional("end")?,
+            ),
+            "PropagateExpression" => {
+                Node::new_propagate_expression(self.token()?, self.child("left")?)
+            }
+            "MemberExpression" => Node::new_member_expression(
+                self.token()?,
+                self.child("left")?,
+                self.child("member")?,
+            ),
+            kind => return Err(format!("unknown node kind: {}", kind)),
+        };
+        let mut node = node.ok_or_else(|| format!("invalid {}: a child has the wrong kind", self.kind))?;
+        if let Some(span) = self.json.get("span") {
+            node.set_span(span_from_json(span)?);
+        }
+        Ok(node)
+    }
+
+    fn field(&self, name: &str) -> Result<&'a Json, String> {
+        self.json
+            .get(name)
+            .ok_or_else(|| format!("missing field {:?} in {}", name, self.kind))
+    }
+
+    fn wrong_type(&self, name: &str, want: &str) -> String {
+        format!("field {:?} in {} must be {}", name, self.kind, want)
+    }
+
+    fn token(&self) -> Result<Token, String> {
+        token_from_json(self.field("token")?)
+    }
+
+    fn child(&self, name: &str) -> Result<Box<Node>, String> {
+        from_json(self.field(name)?)
+    }
+
+    fn children(&self, name: &str) -> Result<Vec<Box<Node>>, String> {
+        match self.field(name)? {
+            Json::Array(elements) => elements.iter().map(from_json).collect(),
+            _ => Err(self.wrong_type(name, "an array")),
+        }
+    }
+
+    fn optional(&self, name: &str) -> Result<Option<Box<Node>>, String> {
+        match self.json.get(name) {
+            None | Some(Json::Null) => Ok(None),
+            Some(json) => from_json(json).map(Some),
+        }
+    }
+
+    fn string(&self, name: &str) -> Result<String, String> {
+        match self.field(name)? {
+            Json::String(s) => Ok(s.to_owned()),
+            _ => Err(self.wrong_type(name, "a string")),
+        }
+    }
+
+    fn integer(&self, name: &str) -> Result<i64, String> {
+        match self.field(name)? {
+            Json::Number(n) => Ok(*n),
+            _ => Err(self.wrong_type(name, "a number")),
+        }
+    }
+
+    fn boolean(&self, name: &str) -> Result<bool, String> {
+        match self.field(name)? {
+            Json::Bool(b) => Ok(*b),
+            _ => Err(self.wrong_type(name, "a boolean")),
+        }
+    }
+}
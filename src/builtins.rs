@@ -0,0 +1,864 @@
+use crate::evaluator::{apply_function, is_truthy, native_bool_to_bool_object, object_to_string};
+use crate::json;
+use crate::json::Json;
+use crate::object;
+use crate::object::{Builtin, HashKey, HashPair, Object};
+use crate::ordered_map::OrderedMap;
+use core::borrow::Borrow;
+use std::cmp::Ordering;
+use std::ops::Deref;
+use std::rc::Rc;
+
+// The kinds of value a builtin parameter can accept.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Type {
+    Any,
+    Integer,
+    String,
+    Array,
+    Hash,
+    Set,
+    Result,
+    Function,
+    Hashable,
+}
+
+impl Type {
+    fn accepts(&self, o: &Object) -> bool {
+        match (self, o) {
+            (Type::Any, _) => true,
+            (Type::Integer, Object::Integer(_)) => true,
+            (Type::String, Object::StringValue(_)) => true,
+            (Type::Array, Object::Array(_)) => true,
+            (Type::Hash, Object::Hash(_)) => true,
+            (Type::Set, Object::Set(_)) => true,
+            (Type::Result, Object::Ok(_)) | (Type::Result, Object::Err(_)) => true,
+            (Type::Function, Object::Function { .. })
+            | (Type::Function, Object::Builtin(_))
+            | (Type::Function, Object::StructType(_))
+            | (Type::Function, Object::VariantType(_)) => true,
+            (Type::Hashable, _) => o.create_hash_key().is_some(),
+            _ => false,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Type::Any => "any value",
+            Type::Integer => "Integer",
+            Type::String => "String",
+            Type::Array => "Array",
+            Type::Hash => "Hash",
+            Type::Set => "Set",
+            Type::Result => "Result",
+            Type::Function => "Function",
+            Type::Hashable => "hashable",
+        }
+    }
+}
+
+// A builtin and the parameters it declares. Each parameter lists the types it accepts;
+// only the first `required` must be given, and a `variadic` builtin repeats its last one.
+struct Signature {
+    name: &'static str,
+    params: &'static [&'static [Type]],
+    required: usize,
+    variadic: bool,
+    function: fn(&Args) -> Result<Rc<Object>, Rc<Object>>,
+}
+
+const fn builtin(
+    name: &'static str,
+    params: &'static [&'static [Type]],
+    function: fn(&Args) -> Result<Rc<Object>, Rc<Object>>,
+) -> Signature {
+    Signature {
+        name,
+        params,
+        required: params.len(),
+        variadic: false,
+        function,
+    }
+}
+
+const ANY: &[Type] = &[Type::Any];
+const INTEGER: &[Type] = &[Type::Integer];
+const STRING: &[Type] = &[Type::String];
+const ARRAY: &[Type] = &[Type::Array];
+const HASH: &[Type] = &[Type::Hash];
+const SET: &[Type] = &[Type::Set];
+const RESULT: &[Type] = &[Type::Result];
+const FUNCTION: &[Type] = &[Type::Function];
+const HASHABLE: &[Type] = &[Type::Hashable];
+const ITERABLE: &[Type] = &[Type::Array, Type::Set];
+
+const BUILTINS: &[Signature] = &[
+    builtin("len", &[&[Type::String, Type::Array, Type::Hash, Type::Set]], builtin_len),
+    builtin("first", &[ARRAY], builtin_first),
+    builtin("last", &[ARRAY], builtin_last),
+    builtin("rest", &[ARRAY], builtin_rest),
+    builtin("push", &[ARRAY, ANY], builtin_push),
+    builtin("ok", &[ANY], builtin_ok),
+    builtin("err", &[ANY], builtin_err),
+    builtin("is_ok", &[RESULT], builtin_is_ok),
+    builtin("is_err", &[RESULT], builtin_is_err),
+    builtin("unwrap", &[RESULT], builtin_unwrap),
+    builtin("unwrap_or", &[RESULT, ANY], builtin_unwrap_or),
+    Signature { required: 0, variadic: true, ..builtin("puts", &[ANY], builtin_puts) },
+    Signature { required: 1, ..builtin("range", &[INTEGER, INTEGER, INTEGER], builtin_range) },
+    builtin("contains", &[&[Type::Array, Type::Set, Type::String], ANY], builtin_contains),
+    builtin("map", &[ITERABLE, FUNCTION], builtin_map),
+    builtin("filter", &[ITERABLE, FUNCTION], builtin_filter),
+    builtin("reduce", &[ITERABLE, ANY, FUNCTION], builtin_reduce),
+    builtin("sort", &[ITERABLE], builtin_sort),
+    builtin("sort_by", &[ITERABLE, FUNCTION], builtin_sort_by),
+    builtin("zip", &[ITERABLE, ITERABLE], builtin_zip),
+    builtin("enumerate", &[ITERABLE], builtin_enumerate),
+    builtin("any", &[ITERABLE, FUNCTION], builtin_any),
+    builtin("all", &[ITERABLE, FUNCTION], builtin_all),
+    builtin("find", &[ITERABLE, FUNCTION], builtin_find),
+    builtin("flat_map", &[ITERABLE, FUNCTION], builtin_flat_map),
+    builtin("split", &[STRING, STRING], builtin_split),
+    builtin("join", &[ITERABLE, STRING], builtin_join),
+    builtin("trim", &[STRING], builtin_trim),
+    builtin("upper", &[STRING], builtin_upper),
+    builtin("lower", &[STRING], builtin_lower),
+    builtin("replace", &[STRING, STRING, STRING], builtin_replace),
+    builtin("starts_with", &[STRING, STRING], builtin_starts_with),
+    builtin("ends_with", &[STRING, STRING], builtin_ends_with),
+    builtin("index_of", &[&[Type::Array, Type::String], ANY], builtin_index_of),
+    Signature { required: 2, ..builtin("substr", &[STRING, INTEGER, INTEGER], builtin_substr) },
+    builtin("repeat", &[STRING, INTEGER], builtin_repeat),
+    builtin("chars", &[STRING], builtin_chars),
+    builtin("to_string", &[ANY], builtin_to_string),
+    builtin("parse_int", &[STRING], builtin_parse_int),
+    builtin("json_parse", &[STRING], builtin_json_parse),
+    Signature { required: 1, ..builtin("json_stringify", &[ANY, INTEGER], builtin_json_stringify) },
+    Signature { required: 2, ..builtin("pad_left", &[STRING, INTEGER, STRING], builtin_pad_left) },
+    Signature { required: 2, ..builtin("pad_right", &[STRING, INTEGER, STRING], builtin_pad_right) },
+    builtin("keys", &[HASH], builtin_keys),
+    builtin("values", &[HASH], builtin_values),
+    builtin("entries", &[HASH], builtin_entries),
+    builtin("has_key", &[HASH, HASHABLE], builtin_has_key),
+    builtin("merge", &[HASH, HASH], builtin_merge),
+    builtin("remove", &[&[Type::Hash, Type::Set], HASHABLE], builtin_remove),
+    builtin("set", &[ITERABLE], builtin_set),
+    builtin("add", &[SET, HASHABLE], builtin_add),
+    builtin("union", &[SET, SET], builtin_union),
+    builtin("intersection", &[SET, SET], builtin_intersection),
+    builtin("difference", &[SET, SET], builtin_difference),
+];
+
+fn signature(name: &str) -> Option<&'static Signature> {
+    BUILTINS.iter().find(|b| b.name == name)
+}
+
+pub(crate) fn find_builtin(s: &str) -> Option<Rc<Object>> {
+    signature(s).map(|_| {
+        Rc::new(Object::Builtin(Rc::new(Builtin {
+            name: s.to_owned(),
+            native: None,
+        })))
+    })
+}
+
+pub(crate) fn do_builtin(s: &str, args: Vec<Rc<Object>>) -> Option<Rc<Object>> {
+    let signature = signature(s)?;
+    if let Some(error) = check_arguments(signature, &args) {
+        return Some(error);
+    }
+    let args = Args { signature, values: args };
+    return Some((signature.function)(&args).unwrap_or_else(|e| e));
+}
+
+// The error for a call that does not match the signature, naming what was expected.
+fn check_arguments(signature: &Signature, args: &[Rc<Object>]) -> Option<Rc<Object>> {
+    let max = signature.params.len();
+    if args.len() < signature.required || (!signature.variadic && args.len() > max) {
+        let want = if signature.variadic {
+            format!("at least {}", signature.required)
+        } else if signature.required == max {
+            max.to_string()
+        } else {
+            format!("{}..{}", signature.required, max)
+        };
+        return Some(Rc::new(Object::Error(format!(
+            "wrong number of arguments, got = {}, want = {}",
+            args.len(),
+            want
+        ))));
+    }
+
+    for (i, arg) in args.iter().enumerate() {
+        if !param_types(signature, i).iter().any(|t| t.accepts(arg)) {
+            return Some(argument_error(signature, i, arg));
+        }
+    }
+
+    return None;
+}
+
+fn param_types(signature: &Signature, i: usize) -> &'static [Type] {
+    signature.params[i.min(signature.params.len() - 1)]
+}
+
+fn argument_error(signature: &Signature, i: usize, arg: &Rc<Object>) -> Rc<Object> {
+    Rc::new(Object::Error(format!(
+        "argument to {} must be {}, got = {:?}",
+        signature.name,
+        type_names(param_types(signature, i)),
+        arg
+    )))
+}
+
+// "Array", "Array or Set", "Array, Set or String".
+fn type_names(types: &[Type]) -> String {
+    let names = types.iter().map(|t| t.name()).collect::<Vec<&str>>();
+    match names.split_last() {
+        Some((last, rest)) if !rest.is_empty() => format!("{} or {}", rest.join(", "), last),
+        _ => names.join(""),
+    }
+}
+
+// The arguments of a call that `check_arguments` accepted. The accessors read an argument
+// as one of the types its parameter declares; asked for another, they give the same
+// error as the check.
+struct Args {
+    signature: &'static Signature,
+    values: Vec<Rc<Object>>,
+}
+
+impl Deref for Args {
+    type Target = [Rc<Object>];
+
+    fn deref(&self) -> &[Rc<Object>] {
+        &self.values
+    }
+}
+
+impl Args {
+    fn mismatch(&self, i: usize) -> Rc<Object> {
+        argument_error(self.signature, i, &self.values[i])
+    }
+
+    fn integer(&self, i: usize) -> Result<i64, Rc<Object>> {
+        match self.values[i].borrow() {
+            Object::Integer(v) => Ok(*v),
+            _ => Err(self.mismatch(i)),
+        }
+    }
+
+    fn string(&self, i: usize) -> Result<&String, Rc<Object>> {
+        match self.values[i].borrow() {
+            Object::StringValue(s) => Ok(s),
+            _ => Err(self.mismatch(i)),
+        }
+    }
+
+    fn array(&self, i: usize) -> Result<&Vec<Rc<Object>>, Rc<Object>> {
+        match self.values[i].borrow() {
+            Object::Array(elements) => Ok(elements),
+            _ => Err(self.mismatch(i)),
+        }
+    }
+
+    fn hash(&self, i: usize) -> Result<&OrderedMap<HashKey, HashPair>, Rc<Object>> {
+        match self.values[i].borrow() {
+            Object::Hash(pairs) => Ok(pairs),
+            _ => Err(self.mismatch(i)),
+        }
+    }
+
+    fn set(&self, i: usize) -> Result<&OrderedMap<HashKey, Rc<Object>>, Rc<Object>> {
+        match self.values[i].borrow() {
+            Object::Set(elements) => Ok(elements),
+            _ => Err(self.mismatch(i)),
+        }
+    }
+
+    // The elements of an Array, or of a Set in insertion order.
+    fn elements(&self, i: usize) -> Result<Vec<Rc<Object>>, Rc<Object>> {
+        match self.values[i].borrow() {
+            Object::Array(elements) => Ok(elements.clone()),
+            Object::Set(elements) => Ok(elements.values().cloned().collect()),
+            _ => Err(self.mismatch(i)),
+        }
+    }
+
+    fn hash_key(&self, i: usize) -> Result<HashKey, Rc<Object>> {
+        self.values[i].create_hash_key().ok_or_else(|| self.mismatch(i))
+    }
+}
+
+fn builtin_len(args: &Args) -> Result<Rc<Object>, Rc<Object>> {
+    let len = match args[0].borrow() {
+        Object::StringValue(v) => v.chars().count(),
+        Object::Array(v) => v.len(),
+        Object::Hash(v) => v.len(),
+        Object::Set(v) => v.len(),
+        _ => return Err(args.mismatch(0)),
+    };
+
+    return Ok(Rc::new(Object::Integer(len as i64)));
+}
+
+fn builtin_first(args: &Args) -> Result<Rc<Object>, Rc<Object>> {
+    let first = args.array(0)?.first().cloned();
+    return Ok(first.unwrap_or_else(|| Rc::new(object::NULL)));
+}
+
+fn builtin_last(args: &Args) -> Result<Rc<Object>, Rc<Object>> {
+    let last = args.array(0)?.last().cloned();
+    return Ok(last.unwrap_or_else(|| Rc::new(object::NULL)));
+}
+
+// rest([1, 2, 3]) is [2, 3]; rest([]) is null.
+fn builtin_rest(args: &Args) -> Result<Rc<Object>, Rc<Object>> {
+    let elements = args.array(0)?;
+    if elements.is_empty() {
+        return Ok(Rc::new(object::NULL));
+    }
+
+    return Ok(Rc::new(Object::Array(elements[1..].to_owned())));
+}
+
+fn builtin_push(args: &Args) -> Result<Rc<Object>, Rc<Object>> {
+    let mut elements = args.array(0)?.to_owned();
+    elements.push(args[1].clone());
+    return Ok(Rc::new(Object::Array(elements)));
+}
+
+fn builtin_ok(args: &Args) -> Result<Rc<Object>, Rc<Object>> {
+    return Ok(Rc::new(Object::Ok(args[0].clone())));
+}
+
+fn builtin_err(args: &Args) -> Result<Rc<Object>, Rc<Object>> {
+    return Ok(Rc::new(Object::Err(args[0].clone())));
+}
+
+fn builtin_is_ok(args: &Args) -> Result<Rc<Object>, Rc<Object>> {
+    return Ok(Rc::new(native_bool_to_bool_object(matches!(args[0].borrow(), Object::Ok(_)))));
+}
+
+fn builtin_is_err(args: &Args) -> Result<Rc<Object>, Rc<Object>> {
+    return Ok(Rc::new(native_bool_to_bool_object(matches!(args[0].borrow(), Object::Err(_)))));
+}
+
+fn builtin_unwrap(args: &Args) -> Result<Rc<Object>, Rc<Object>> {
+    match args[0].borrow() {
+        Object::Ok(v) => Ok(v.clone()),
+        Object::Err(e) => Err(Rc::new(Object::Error(format!("called unwrap on err: {}", e)))),
+        _ => Err(args.mismatch(0)),
+    }
+}
+
+fn builtin_unwrap_or(args: &Args) -> Result<Rc<Object>, Rc<Object>> {
+    match args[0].borrow() {
+        Object::Ok(v) => Ok(v.clone()),
+        Object::Err(_) => Ok(args[1].clone()),
+        _ => Err(args.mismatch(0)),
+    }
+}
+
+fn builtin_puts(args: &Args) -> Result<Rc<Object>, Rc<Object>> {
+    for arg in args.iter() {
+        println!("{}", object_to_string(arg)?);
+    }
+
+    return Ok(Rc::new(object::NULL));
+}
+
+// range(end), range(start, end) or range(start, end, step); end is exclusive.
+fn builtin_range(args: &Args) -> Result<Rc<Object>, Rc<Object>> {
+    let (start, end) = match args.len() {
+        1 => (0, args.integer(0)?),
+        _ => (args.integer(0)?, args.integer(1)?),
+    };
+    let step = match args.len() {
+        3 => args.integer(2)?,
+        _ => 1,
+    };
+    if step == 0 {
+        return Err(Rc::new(Object::Error("range step must not be 0".to_owned())));
+    }
+
+    let mut elements = vec![];
+    let mut i = start;
+    while (step > 0 && i < end) || (step < 0 && i > end) {
+        elements.push(Rc::new(Object::Integer(i)));
+        i = match i.checked_add(step) {
+            Some(next) => next,
+            None => break,
+        };
+    }
+
+    return Ok(Rc::new(Object::Array(elements)));
+}
+
+fn builtin_contains(args: &Args) -> Result<Rc<Object>, Rc<Object>> {
+    let elm = &args[1];
+    let found = match (args[0].borrow(), elm.borrow()) {
+        (Object::Array(elements), _) => elements.contains(elm),
+        (Object::Set(elements), _) => elm.create_hash_key().filter(|key| elements.contains_key(key)).is_some(),
+        (Object::StringValue(s), Object::StringValue(sub)) => s.contains(sub.as_str()),
+        (Object::StringValue(_), _) => {
+            return Err(Rc::new(Object::Error(format!("argument to contains must be String, got = {:?}", elm))))
+        }
+        _ => return Err(args.mismatch(0)),
+    };
+
+    return Ok(Rc::new(native_bool_to_bool_object(found)));
+}
+
+// Calls a Monkey function (or another builtin) on behalf of a builtin. An error
+// raised by the call comes back as `Err` so the builtin can return it unchanged.
+fn call(function: &Rc<Object>, args: Vec<Rc<Object>>) -> Result<Rc<Object>, Rc<Object>> {
+    match apply_function(function.clone(), args) {
+        Some(result) if result.is_error() => Err(result),
+        Some(result) => Ok(result),
+        None => Err(Rc::new(Object::Error(format!("not a function: {}", function)))),
+    }
+}
+
+fn builtin_map(args: &Args) -> Result<Rc<Object>, Rc<Object>> {
+    let mut mapped = vec![];
+    for e in args.elements(0)?.into_iter() {
+        mapped.push(call(&args[1], vec![e])?);
+    }
+
+    return Ok(Rc::new(Object::Array(mapped)));
+}
+
+fn builtin_filter(args: &Args) -> Result<Rc<Object>, Rc<Object>> {
+    let mut filtered = vec![];
+    for e in args.elements(0)?.into_iter() {
+        if is_truthy(call(&args[1], vec![e.clone()])?) {
+            filtered.push(e);
+        }
+    }
+
+    return Ok(Rc::new(Object::Array(filtered)));
+}
+
+fn builtin_reduce(args: &Args) -> Result<Rc<Object>, Rc<Object>> {
+    let mut acc = args[1].clone();
+    for e in args.elements(0)?.into_iter() {
+        acc = call(&args[2], vec![acc, e])?;
+    }
+
+    return Ok(acc);
+}
+
+fn builtin_sort(args: &Args) -> Result<Rc<Object>, Rc<Object>> {
+    let sorted = merge_sort(&args.elements(0)?, &mut |a, b| match (a.borrow(), b.borrow()) {
+        (Object::Integer(a), Object::Integer(b)) => Ok(a.cmp(b)),
+        (Object::StringValue(a), Object::StringValue(b)) => Ok(a.cmp(b)),
+        _ => Err(Rc::new(Object::Error(format!("cannot compare {} with {} in sort", a, b)))),
+    })?;
+
+    return Ok(Rc::new(Object::Array(sorted)));
+}
+
+// sort_by(arr, fn(a, b) { ... }) keeps equal elements in order; the comparator
+// returns a negative Integer when a goes first, 0 when equal and positive otherwise.
+fn builtin_sort_by(args: &Args) -> Result<Rc<Object>, Rc<Object>> {
+    let sorted = merge_sort(&args.elements(0)?, &mut |a, b| {
+        let order = call(&args[1], vec![a.clone(), b.clone()])?;
+        match order.borrow() {
+            Object::Integer(i) => Ok(i.cmp(&0)),
+            _ => Err(Rc::new(Object::Error(format!("comparator must return Integer, got = {:?}", order)))),
+        }
+    })?;
+
+    return Ok(Rc::new(Object::Array(sorted)));
+}
+
+// A stable merge sort whose comparator may fail; unlike `slice::sort_by` it also
+// tolerates comparators that are not a total order.
+fn merge_sort(
+    elements: &[Rc<Object>],
+    compare: &mut dyn FnMut(&Rc<Object>, &Rc<Object>) -> Result<Ordering, Rc<Object>>,
+) -> Result<Vec<Rc<Object>>, Rc<Object>> {
+    if elements.len() <= 1 {
+        return Ok(elements.to_vec());
+    }
+
+    let (left, right) = elements.split_at(elements.len() / 2);
+    let left = merge_sort(left, compare)?;
+    let right = merge_sort(right, compare)?;
+
+    let mut merged = Vec::with_capacity(elements.len());
+    let (mut i, mut j) = (0, 0);
+    while i < left.len() && j < right.len() {
+        if compare(&right[j], &left[i])? == Ordering::Less {
+            merged.push(right[j].clone());
+            j += 1;
+        } else {
+            merged.push(left[i].clone());
+            i += 1;
+        }
+    }
+    merged.extend_from_slice(&left[i..]);
+    merged.extend_from_slice(&right[j..]);
+
+    return Ok(merged);
+}
+
+fn builtin_zip(args: &Args) -> Result<Rc<Object>, Rc<Object>> {
+    let left = args.elements(0)?;
+    let right = args.elements(1)?;
+    let pairs = left
+        .iter()
+        .zip(right.iter())
+        .map(|(a, b)| Rc::new(Object::Array(vec![a.clone(), b.clone()])))
+        .collect();
+
+    return Ok(Rc::new(Object::Array(pairs)));
+}
+
+fn builtin_enumerate(args: &Args) -> Result<Rc<Object>, Rc<Object>> {
+    let pairs = args
+        .elements(0)?
+        .into_iter()
+        .enumerate()
+        .map(|(i, e)| Rc::new(Object::Array(vec![Rc::new(Object::Integer(i as i64)), e])))
+        .collect();
+
+    return Ok(Rc::new(Object::Array(pairs)));
+}
+
+fn builtin_any(args: &Args) -> Result<Rc<Object>, Rc<Object>> {
+    for e in args.elements(0)?.into_iter() {
+        if is_truthy(call(&args[1], vec![e])?) {
+            return Ok(Rc::new(object::TRUE));
+        }
+    }
+
+    return Ok(Rc::new(object::FALSE));
+}
+
+fn builtin_all(args: &Args) -> Result<Rc<Object>, Rc<Object>> {
+    for e in args.elements(0)?.into_iter() {
+        if !is_truthy(call(&args[1], vec![e])?) {
+            return Ok(Rc::new(object::FALSE));
+        }
+    }
+
+    return Ok(Rc::new(object::TRUE));
+}
+
+fn builtin_find(args: &Args) -> Result<Rc<Object>, Rc<Object>> {
+    for e in args.elements(0)?.into_iter() {
+        if is_truthy(call(&args[1], vec![e.clone()])?) {
+            return Ok(e);
+        }
+    }
+
+    return Ok(Rc::new(object::NULL));
+}
+
+fn builtin_flat_map(args: &Args) -> Result<Rc<Object>, Rc<Object>> {
+    let mut flattened = vec![];
+    for e in args.elements(0)?.into_iter() {
+        let mapped = call(&args[1], vec![e])?;
+        match mapped.borrow() {
+            Object::Array(inner) => flattened.extend(inner.iter().cloned()),
+            _ => {
+                return Err(Rc::new(Object::Error(format!(
+                    "flat_map function must return Array, got = {:?}",
+                    mapped
+                ))))
+            }
+        }
+    }
+
+    return Ok(Rc::new(Object::Array(flattened)));
+}
+
+fn new_string(s: String) -> Rc<Object> {
+    Rc::new(Object::StringValue(s))
+}
+
+fn new_string_array<'a>(strings: impl Iterator<Item = &'a str>) -> Rc<Object> {
+    Rc::new(Object::Array(strings.map(|s| new_string(s.to_owned())).collect()))
+}
+
+// split("a,b", ",") is ["a", "b"]; an empty separator splits into characters.
+fn builtin_split(args: &Args) -> Result<Rc<Object>, Rc<Object>> {
+    let s = args.string(0)?;
+    let separator = args.string(1)?;
+    if separator.is_empty() {
+        return Ok(Rc::new(Object::Array(s.chars().map(|c| new_string(c.to_string())).collect())));
+    }
+
+    return Ok(new_string_array(s.split(separator.as_str())));
+}
+
+fn builtin_join(args: &Args) -> Result<Rc<Object>, Rc<Object>> {
+    let strings = args
+        .elements(0)?
+        .iter()
+        .map(object_to_string)
+        .collect::<Result<Vec<String>, Rc<Object>>>()?;
+
+    return Ok(new_string(strings.join(args.string(1)?)));
+}
+
+fn builtin_trim(args: &Args) -> Result<Rc<Object>, Rc<Object>> {
+    return Ok(new_string(args.string(0)?.trim().to_owned()));
+}
+
+fn builtin_upper(args: &Args) -> Result<Rc<Object>, Rc<Object>> {
+    return Ok(new_string(args.string(0)?.to_uppercase()));
+}
+
+fn builtin_lower(args: &Args) -> Result<Rc<Object>, Rc<Object>> {
+    return Ok(new_string(args.string(0)?.to_lowercase()));
+}
+
+fn builtin_replace(args: &Args) -> Result<Rc<Object>, Rc<Object>> {
+    return Ok(new_string(args.string(0)?.replace(args.string(1)?.as_str(), args.string(2)?)));
+}
+
+fn builtin_starts_with(args: &Args) -> Result<Rc<Object>, Rc<Object>> {
+    let found = args.string(0)?.starts_with(args.string(1)?.as_str());
+    return Ok(Rc::new(native_bool_to_bool_object(found)));
+}
+
+fn builtin_ends_with(args: &Args) -> Result<Rc<Object>, Rc<Object>> {
+    let found = args.string(0)?.ends_with(args.string(1)?.as_str());
+    return Ok(Rc::new(native_bool_to_bool_object(found)));
+}
+
+// The character index of the first occurrence in a String, or the position of the
+// first equal element in an Array; -1 when there is none.
+fn builtin_index_of(args: &Args) -> Result<Rc<Object>, Rc<Object>> {
+    let index = match (args[0].borrow(), args[1].borrow()) {
+        (Object::StringValue(s), Object::StringValue(sub)) => {
+            s.find(sub.as_str()).map(|byte| s[..byte].chars().count())
+        }
+        (Object::StringValue(_), _) => {
+            return Err(Rc::new(Object::Error(format!(
+                "argument to index_of must be String, got = {:?}",
+                args[1]
+            ))))
+        }
+        (Object::Array(elements), _) => elements.iter().position(|e| *e == args[1]),
+        _ => return Err(args.mismatch(0)),
+    };
+
+    return Ok(Rc::new(Object::Integer(index.map(|i| i as i64).unwrap_or(-1))));
+}
+
+// substr(s, start) or substr(s, start, length), counted in characters.
+fn builtin_substr(args: &Args) -> Result<Rc<Object>, Rc<Object>> {
+    let start = args.integer(1)?.max(0) as usize;
+    let length = match args.len() {
+        3 => args.integer(2)?.max(0) as usize,
+        _ => usize::MAX,
+    };
+
+    return Ok(new_string(args.string(0)?.chars().skip(start).take(length).collect()));
+}
+
+// The longest string `repeat` builds, so a large count fails instead of exhausting memory.
+const MAX_REPEAT_LEN: usize = 1 << 30;
+
+fn builtin_repeat(args: &Args) -> Result<Rc<Object>, Rc<Object>> {
+    let s = args.string(0)?;
+    let count = args.integer(1)?;
+    if count < 0 {
+        return Err(Rc::new(Object::Error(format!("repeat count must not be negative, got = {}", count))));
+    }
+
+    repeat_limited(s, count as usize).map(new_string)
+}
+
+fn repeat_limited(s: &str, count: usize) -> Result<String, Rc<Object>> {
+    match s.len().checked_mul(count) {
+        Some(len) if len <= MAX_REPEAT_LEN => Ok(s.repeat(count)),
+        _ => Err(Rc::new(Object::Error(format!(
+            "repeat result too long, limit = {} bytes",
+            MAX_REPEAT_LEN
+        )))),
+    }
+}
+
+fn builtin_chars(args: &Args) -> Result<Rc<Object>, Rc<Object>> {
+    let chars = args.string(0)?.chars().map(|c| new_string(c.to_string())).collect();
+    return Ok(Rc::new(Object::Array(chars)));
+}
+
+fn builtin_to_string(args: &Args) -> Result<Rc<Object>, Rc<Object>> {
+    return object_to_string(&args[0]).map(new_string);
+}
+
+fn builtin_parse_int(args: &Args) -> Result<Rc<Object>, Rc<Object>> {
+    let s = args.string(0)?;
+    match s.parse::<i64>() {
+        Ok(i) => Ok(Rc::new(Object::Integer(i))),
+        Err(_) => Err(Rc::new(Object::Error(format!("could not parse as Integer: {:?}", s)))),
+    }
+}
+
+fn builtin_json_parse(args: &Args) -> Result<Rc<Object>, Rc<Object>> {
+    return Json::parse(args.string(0)?)
+        .map(|json| json::to_value(&json))
+        .map_err(|e| Rc::new(Object::Error(e)));
+}
+
+// json_stringify(value) writes compact JSON; json_stringify(value, indent) puts each
+// array element and object member on its own line, indented by indent spaces per level.
+fn builtin_json_stringify(args: &Args) -> Result<Rc<Object>, Rc<Object>> {
+    let indent = match args.len() {
+        2 => match args.integer(1)? {
+            i if i < 0 => return Err(Rc::new(Object::Error(format!("indent must not be negative, got = {}", i)))),
+            i => Some(i as usize),
+        },
+        _ => None,
+    };
+
+    return json::from_value(&args[0])
+        .map(|json| new_string(json.stringify(indent)))
+        .map_err(|e| Rc::new(Object::Error(e)));
+}
+
+fn builtin_pad_left(args: &Args) -> Result<Rc<Object>, Rc<Object>> {
+    return pad(args, |s, padding| padding + s);
+}
+
+fn builtin_pad_right(args: &Args) -> Result<Rc<Object>, Rc<Object>> {
+    return pad(args, |s, padding| s.to_owned() + &padding);
+}
+
+// pad_left(s, width) or pad_left(s, width, fill) pads with spaces or the single
+// character fill until s is width characters long.
+fn pad(args: &Args, join: fn(&str, String) -> String) -> Result<Rc<Object>, Rc<Object>> {
+    let s = args.string(0)?;
+    let width = args.integer(1)?.max(0) as usize;
+    let fill = match args.len() {
+        3 => args.string(2)?.as_str(),
+        _ => " ",
+    };
+    if fill.chars().count() != 1 {
+        return Err(Rc::new(Object::Error(format!(
+            "{} fill must be a single character, got = {:?}",
+            args.signature.name, fill
+        ))));
+    }
+
+    let padding = repeat_limited(fill, width.saturating_sub(s.chars().count()))?;
+    return Ok(new_string(join(s, padding)));
+}
+
+fn builtin_keys(args: &Args) -> Result<Rc<Object>, Rc<Object>> {
+    let keys = args.hash(0)?.values().map(|p| p.key.clone()).collect();
+    return Ok(Rc::new(Object::Array(keys)));
+}
+
+fn builtin_values(args: &Args) -> Result<Rc<Object>, Rc<Object>> {
+    let values = args.hash(0)?.values().map(|p| p.value.clone()).collect();
+    return Ok(Rc::new(Object::Array(values)));
+}
+
+// entries({"a": 1}) is [["a", 1]].
+fn builtin_entries(args: &Args) -> Result<Rc<Object>, Rc<Object>> {
+    let entries = args
+        .hash(0)?
+        .values()
+        .map(|p| Rc::new(Object::Array(vec![p.key.clone(), p.value.clone()])))
+        .collect();
+
+    return Ok(Rc::new(Object::Array(entries)));
+}
+
+fn builtin_has_key(args: &Args) -> Result<Rc<Object>, Rc<Object>> {
+    let found = args.hash(0)?.contains_key(&args.hash_key(1)?);
+    return Ok(Rc::new(native_bool_to_bool_object(found)));
+}
+
+// merge(a, b) is a new hash with the pairs of both; b wins where they share a key.
+fn builtin_merge(args: &Args) -> Result<Rc<Object>, Rc<Object>> {
+    let mut pairs = args.hash(0)?.clone();
+    pairs.extend(args.hash(1)?.iter().map(|(k, p)| (k.clone(), p.clone())));
+    return Ok(Rc::new(Object::Hash(pairs)));
+}
+
+// remove(h, key) is a new hash without key, and remove(s, x) a new set without x;
+// the argument itself is unchanged.
+fn builtin_remove(args: &Args) -> Result<Rc<Object>, Rc<Object>> {
+    let key = args.hash_key(1)?;
+    match args[0].borrow() {
+        Object::Hash(pairs) => {
+            let mut pairs = pairs.clone();
+            pairs.remove(&key);
+            Ok(Rc::new(Object::Hash(pairs)))
+        }
+        Object::Set(elements) => {
+            let mut elements = elements.clone();
+            elements.remove(&key);
+            Ok(Rc::new(Object::Set(elements)))
+        }
+        _ => Err(args.mismatch(0)),
+    }
+}
+
+// set([3, 1, 3]) is #{3, 1}.
+fn builtin_set(args: &Args) -> Result<Rc<Object>, Rc<Object>> {
+    let mut set = OrderedMap::new();
+    for e in args.elements(0)?.into_iter() {
+        let key = e
+            .create_hash_key()
+            .ok_or_else(|| Rc::new(Object::Error(format!("argument to set must be hashable, got = {}", e))))?;
+        set.insert(key, e);
+    }
+
+    return Ok(Rc::new(Object::Set(set)));
+}
+
+fn builtin_add(args: &Args) -> Result<Rc<Object>, Rc<Object>> {
+    let key = args.hash_key(1)?;
+    let mut elements = args.set(0)?.clone();
+    if !elements.contains_key(&key) {
+        elements.insert(key, args[1].clone());
+    }
+
+    return Ok(Rc::new(Object::Set(elements)));
+}
+
+fn builtin_union(args: &Args) -> Result<Rc<Object>, Rc<Object>> {
+    return combine_sets(args, |_, _| true, true);
+}
+
+fn builtin_intersection(args: &Args) -> Result<Rc<Object>, Rc<Object>> {
+    return combine_sets(args, |right, key| right.contains_key(key), false);
+}
+
+fn builtin_difference(args: &Args) -> Result<Rc<Object>, Rc<Object>> {
+    return combine_sets(args, |right, key| !right.contains_key(key), false);
+}
+
+// Keeps the elements of the left set that `keep` accepts, in order, followed by the
+// new elements of the right set when `with_right` is set.
+fn combine_sets(
+    args: &Args,
+    keep: fn(&OrderedMap<HashKey, Rc<Object>>, &HashKey) -> bool,
+    with_right: bool,
+) -> Result<Rc<Object>, Rc<Object>> {
+    let left = args.set(0)?;
+    let right = args.set(1)?;
+    let mut elements = left
+        .iter()
+        .filter(|(key, _)| keep(right, key))
+        .map(|(key, e)| (key.clone(), e.clone()))
+        .collect::<OrderedMap<HashKey, Rc<Object>>>();
+    if with_right {
+        for (key, e) in right.iter() {
+            if !elements.contains_key(key) {
+                elements.insert(key.clone(), e.clone());
+            }
+        }
+    }
+
+    return Ok(Rc::new(Object::Set(elements)));
+}
@@ -0,0 +1,329 @@
+use crate::native;
+use crate::object::{HashKey, HashPair, Object, Value};
+use crate::ordered_map::OrderedMap;
+
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fmt;
+use std::rc::Rc;
+
+/// Conversion of a Rust value into a Monkey value.
+pub trait IntoMonkey {
+    fn into_monkey(self) -> Value;
+}
+
+/// Conversion of a Monkey value into a Rust value, failing if the value has the wrong type.
+pub trait FromMonkey: Sized {
+    fn from_monkey(value: &Value) -> Result<Self, ConversionError>;
+}
+
+/// A Monkey value did not have the type a Rust value needed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConversionError {
+    pub expected: String,
+    pub actual: String,
+    // What was wrong with a value of the right type, such as an Integer out of range.
+    detail: Option<String>,
+    // The hash fields and array indices leading to the value, outermost first.
+    path: Vec<String>,
+}
+
+impl ConversionError {
+    pub fn new<S: Into<String>>(expected: S, actual: &Object) -> ConversionError {
+        ConversionError {
+            expected: expected.into(),
+            actual: actual.type_name().to_owned(),
+            detail: None,
+            path: vec![],
+        }
+    }
+
+    fn with_detail(mut self, detail: String) -> ConversionError {
+        self.detail = Some(detail);
+        self
+    }
+
+    // The same error for a value nested in `field` of an outer value.
+    fn within(mut self, field: String) -> ConversionError {
+        self.path.insert(0, field);
+        self
+    }
+}
+
+impl fmt::Display for ConversionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if !self.path.is_empty() {
+            write!(f, "{}: ", self.path.join("."))?;
+        }
+        write!(f, "expected {}, got = {}", self.expected, self.actual)?;
+        if let Some(detail) = &self.detail {
+            write!(f, " {}", detail)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ConversionError {}
+
+impl From<ConversionError> for native::Error {
+    fn from(error: ConversionError) -> native::Error {
+        native::Error::new(error.to_string())
+    }
+}
+
+/// Converts `value`, or reports the Monkey types involved.
+pub fn from_monkey<T: FromMonkey>(value: &Value) -> Result<T, ConversionError> {
+    T::from_monkey(value)
+}
+
+pub fn into_monkey<T: IntoMonkey>(value: T) -> Value {
+    value.into_monkey()
+}
+
+impl IntoMonkey for Value {
+    fn into_monkey(self) -> Value {
+        self
+    }
+}
+
+impl FromMonkey for Value {
+    fn from_monkey(value: &Value) -> Result<Value, ConversionError> {
+        Ok(value.clone())
+    }
+}
+
+impl IntoMonkey for () {
+    fn into_monkey(self) -> Value {
+        Rc::new(Object::Null)
+    }
+}
+
+impl FromMonkey for () {
+    fn from_monkey(value: &Value) -> Result<(), ConversionError> {
+        match value.as_ref() {
+            Object::Null => Ok(()),
+            o => Err(ConversionError::new("Null", o)),
+        }
+    }
+}
+
+impl IntoMonkey for bool {
+    fn into_monkey(self) -> Value {
+        Rc::new(Object::Bool(self))
+    }
+}
+
+impl FromMonkey for bool {
+    fn from_monkey(value: &Value) -> Result<bool, ConversionError> {
+        match value.as_ref() {
+            Object::Bool(v) => Ok(*v),
+            o => Err(ConversionError::new("Bool", o)),
+        }
+    }
+}
+
+// Every integer type converts from an Integer in its range; only the types that always
+// fit in an i64 convert back without a check.
+macro_rules! integer_conversions {
+    ($($into:ty),*; $($from_only:ty),*) => {
+        $(
+            impl IntoMonkey for $into {
+                fn into_monkey(self) -> Value {
+                    Rc::new(Object::Integer(i64::from(self)))
+                }
+            }
+        )*
+        $(integer_conversions!(@from $into);)*
+        $(integer_conversions!(@from $from_only);)*
+    };
+    (@from $t:ty) => {
+        impl FromMonkey for $t {
+            fn from_monkey(value: &Value) -> Result<$t, ConversionError> {
+                match value.as_ref() {
+                    Object::Integer(v) => <$t>::try_from(*v).map_err(|_| {
+                        ConversionError::new(format!("Integer in range of {}", stringify!($t)), value)
+                            .with_detail(v.to_string())
+                    }),
+                    o => Err(ConversionError::new("Integer", o)),
+                }
+            }
+        }
+    };
+}
+
+integer_conversions!(i8, i16, i32, i64, u8, u16, u32; u64, isize, usize);
+
+impl IntoMonkey for String {
+    fn into_monkey(self) -> Value {
+        Rc::new(Object::StringValue(self))
+    }
+}
+
+impl IntoMonkey for &str {
+    fn into_monkey(self) -> Value {
+        Rc::new(Object::StringValue(self.to_owned()))
+    }
+}
+
+impl FromMonkey for String {
+    fn from_monkey(value: &Value) -> Result<String, ConversionError> {
+        match value.as_ref() {
+            Object::StringValue(v) => Ok(v.to_owned()),
+            o => Err(ConversionError::new("String", o)),
+        }
+    }
+}
+
+// `None` is null, and null converts to `None`.
+impl<T: IntoMonkey> IntoMonkey for Option<T> {
+    fn into_monkey(self) -> Value {
+        match self {
+            Some(v) => v.into_monkey(),
+            None => Rc::new(Object::Null),
+        }
+    }
+}
+
+impl<T: FromMonkey> FromMonkey for Option<T> {
+    fn from_monkey(value: &Value) -> Result<Option<T>, ConversionError> {
+        match value.as_ref() {
+            Object::Null => Ok(None),
+            _ => T::from_monkey(value).map(Some),
+        }
+    }
+}
+
+impl<T: IntoMonkey> IntoMonkey for Vec<T> {
+    fn into_monkey(self) -> Value {
+        Rc::new(Object::Array(
+            self.into_iter().map(IntoMonkey::into_monkey).collect(),
+        ))
+    }
+}
+
+impl<T: FromMonkey> FromMonkey for Vec<T> {
+    fn from_monkey(value: &Value) -> Result<Vec<T>, ConversionError> {
+        match value.as_ref() {
+            Object::Array(elements) => elements
+                .iter()
+                .enumerate()
+                .map(|(i, e)| T::from_monkey(e).map_err(|err| err.within(i.to_string())))
+                .collect(),
+            o => Err(ConversionError::new("Array", o)),
+        }
+    }
+}
+
+// Keys are sorted so the same map always produces the same hash.
+impl<T: IntoMonkey> IntoMonkey for HashMap<String, T> {
+    fn into_monkey(self) -> Value {
+        let mut entries = self.into_iter().collect::<Vec<(String, T)>>();
+        entries.sort_by(|(l, _), (r, _)| l.cmp(r));
+        let mut hash = HashBuilder::new();
+        for (key, value) in entries {
+            hash = hash.field(&key, value);
+        }
+        hash.build()
+    }
+}
+
+impl<T: FromMonkey> FromMonkey for HashMap<String, T> {
+    fn from_monkey(value: &Value) -> Result<HashMap<String, T>, ConversionError> {
+        let pairs = match value.as_ref() {
+            Object::Hash(pairs) => pairs,
+            o => return Err(ConversionError::new("Hash", o)),
+        };
+        let mut map = HashMap::new();
+        for pair in pairs.values() {
+            let key = String::from_monkey(&pair.key)?;
+            let value = T::from_monkey(&pair.value).map_err(|e| e.within(key.clone()))?;
+            map.insert(key, value);
+        }
+        Ok(map)
+    }
+}
+
+// Tuples are arrays of exactly their length.
+macro_rules! tuple_conversions {
+    ($(($($t:ident $i:tt),+) => $len:expr;)*) => {
+        $(
+            impl<$($t: IntoMonkey),+> IntoMonkey for ($($t,)+) {
+                fn into_monkey(self) -> Value {
+                    Rc::new(Object::Array(vec![$(self.$i.into_monkey()),+]))
+                }
+            }
+
+            impl<$($t: FromMonkey),+> FromMonkey for ($($t,)+) {
+                fn from_monkey(value: &Value) -> Result<($($t,)+), ConversionError> {
+                    let expected = format!("Array of length {}", $len);
+                    match value.as_ref() {
+                        Object::Array(elements) if elements.len() == $len => Ok(($(
+                            $t::from_monkey(&elements[$i])
+                                .map_err(|e| e.within(stringify!($i).to_owned()))?,
+                        )+)),
+                        Object::Array(elements) => Err(ConversionError::new(expected, value)
+                            .with_detail(format!("of length {}", elements.len()))),
+                        o => Err(ConversionError::new(expected, o)),
+                    }
+                }
+            }
+        )*
+    };
+}
+
+tuple_conversions! {
+    (A 0) => 1;
+    (A 0, B 1) => 2;
+    (A 0, B 1, C 2) => 3;
+    (A 0, B 1, C 2, D 3) => 4;
+}
+
+/// Builds a Monkey hash with string keys, in the order the fields are added; a host
+/// struct implements `IntoMonkey` by adding each of its fields.
+#[derive(Default)]
+pub struct HashBuilder {
+    pairs: OrderedMap<HashKey, HashPair>,
+}
+
+impl HashBuilder {
+    pub fn new() -> HashBuilder {
+        HashBuilder::default()
+    }
+
+    pub fn field<T: IntoMonkey>(mut self, name: &str, value: T) -> HashBuilder {
+        let key = Rc::new(Object::StringValue(name.to_owned()));
+        self.pairs.insert(
+            HashKey::String(name.to_owned()),
+            HashPair {
+                key,
+                value: value.into_monkey(),
+            },
+        );
+        self
+    }
+
+    pub fn build(self) -> Value {
+        Rc::new(Object::Hash(self.pairs))
+    }
+}
+
+/// The field `name` of the hash or struct `value`, for implementing `FromMonkey` on a
+/// host struct. A missing field converts like null, so `Option` fields may be left out.
+pub fn field<T: FromMonkey>(value: &Value, name: &str) -> Result<T, ConversionError> {
+    let field = match value.as_ref() {
+        Object::Hash(pairs) => pairs
+            .get(&HashKey::String(name.to_owned()))
+            .map(|pair| pair.value.clone()),
+        Object::Struct {
+            struct_type,
+            values,
+        } => struct_type
+            .fields
+            .iter()
+            .position(|f| f == name)
+            .map(|i| values[i].clone()),
+        o => return Err(ConversionError::new("Hash or Struct", o)),
+    };
+    let field = field.unwrap_or_else(|| Rc::new(Object::Null));
+    T::from_monkey(&field).map_err(|e| e.within(name.to_owned()))
+}
@@ -1,33 +1,199 @@
-use crate::object::Object;
+use crate::builtins::find_builtin;
+use crate::native;
+use crate::object::{Builtin, Object, Value};
+use crate::prelude;
 
-use std::collections::HashMap;
-use std::rc::Rc;
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::mem;
+use std::path::{Path, PathBuf};
+use std::rc::{Rc, Weak};
 
-#[derive(Debug, Clone)]
+type Store = RefCell<HashMap<String, Rc<Object>>>;
+
+/// The bindings of one scope and the scopes around it.
+///
+/// A function keeps the scope it was defined in, not a copy of it, so it sees the bindings
+/// there as they are when it is called: a later `let` of the same name is visible to it.
+/// That is what lets hoisted functions call each other. The global environment made by
+/// `new` or `new_with_prelude` owns the scopes functions kept, and clears them when it is
+/// dropped; functions taken out of it stop seeing their bindings then.
 pub struct Environment {
-    store: HashMap<String, Rc<Object>>,
+    store: Rc<Store>,
     outer: Option<Rc<Environment>>,
+    prelude: Option<Rc<Environment>>,
+    host: Rc<RefCell<HostBuiltins>>,
+    modules: Rc<RefCell<HashMap<PathBuf, Value>>>,
+    duplicate_keys: Rc<Cell<DuplicateKeyPolicy>>,
+    captured: Rc<RefCell<Vec<Weak<Store>>>>,
+    owner: bool,
+}
+
+/// What a hash literal that repeats a key does: keep the last value, or fail.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DuplicateKeyPolicy {
+    Allow,
+    Error,
+}
+
+// Builtins the host registered or removed, shared by every environment made from the
+// same global one, including the environments of imported modules.
+#[derive(Default)]
+struct HostBuiltins {
+    registered: HashMap<String, Rc<Object>>,
+    removed: HashSet<String>,
 }
 
 impl Environment {
     pub fn new() -> Environment {
         Environment {
-            store: HashMap::new(),
+            store: Rc::new(RefCell::new(HashMap::new())),
             outer: None,
+            prelude: None,
+            host: Rc::new(RefCell::new(HostBuiltins::default())),
+            modules: Rc::new(RefCell::new(HashMap::new())),
+            duplicate_keys: Rc::new(Cell::new(DuplicateKeyPolicy::Allow)),
+            captured: Rc::new(RefCell::new(Vec::new())),
+            owner: true,
         }
     }
 
+    /// A global environment whose scripts can call the prelude functions.
+    pub fn new_with_prelude() -> Environment {
+        match Environment::new_with_prelude_source(prelude::PRELUDE) {
+            Ok(env) => env,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    // The prelude is evaluated into an environment of its own rather than the globals, so
+    // that it ranks with the builtins: below script bindings and host registrations.
+    pub(crate) fn new_with_prelude_source(source: &str) -> Result<Environment, String> {
+        let mut env = Environment::new();
+        let mut functions = env.new_global();
+        prelude::load_source(&mut functions, source)?;
+        env.prelude = Some(Rc::new(functions));
+        Ok(env)
+    }
+
     pub fn new_enclosed(outer: Rc<Environment>) -> Environment {
         Environment {
-            store: HashMap::new(),
+            store: Rc::new(RefCell::new(HashMap::new())),
+            prelude: outer.prelude.clone(),
+            host: outer.host.clone(),
+            modules: outer.modules.clone(),
+            duplicate_keys: outer.duplicate_keys.clone(),
+            captured: outer.captured.clone(),
+            owner: false,
             outer: Some(outer),
         }
     }
 
+    /// A fresh global environment for an imported module: it shares the host
+    /// builtins, prelude, loaded modules and settings of this one, but none of its bindings.
+    pub fn new_module(&self) -> Environment {
+        self.new_global()
+    }
+
+    // A scope without an outer one that is still owned by this environment's owner, since
+    // the functions defined in it usually outlive it.
+    fn new_global(&self) -> Environment {
+        let env = Environment {
+            store: Rc::new(RefCell::new(HashMap::new())),
+            outer: None,
+            prelude: self.prelude.clone(),
+            host: self.host.clone(),
+            modules: self.modules.clone(),
+            duplicate_keys: self.duplicate_keys.clone(),
+            captured: self.captured.clone(),
+            owner: false,
+        };
+        env.captured.borrow_mut().push(Rc::downgrade(&env.store));
+        env
+    }
+
+    /// Ends the scope this environment was made for. When a function defined in it still
+    /// holds it, the owning global environment takes it over.
+    pub(crate) fn close(self) {
+        if Rc::strong_count(&self.store) == 1 {
+            return;
+        }
+        let mut captured = self.captured.borrow_mut();
+        if captured.len() == captured.capacity() {
+            captured.retain(|scope| scope.strong_count() > 0);
+        }
+        captured.push(Rc::downgrade(&self.store));
+    }
+
+    /// Sets what hash literals repeating a key do here and in every environment made
+    /// from this one. The default is `DuplicateKeyPolicy::Allow`.
+    pub fn set_duplicate_key_policy(&mut self, policy: DuplicateKeyPolicy) {
+        self.duplicate_keys.set(policy);
+    }
+
+    pub fn duplicate_key_policy(&self) -> DuplicateKeyPolicy {
+        self.duplicate_keys.get()
+    }
+
+    // Modules are evaluated once per global environment and the environments made from it,
+    // keyed by canonical path, so each interpreter sees modules built with its own builtins.
+    pub(crate) fn loaded_module(&self, path: &Path) -> Option<Value> {
+        self.modules.borrow().get(path).cloned()
+    }
+
+    pub(crate) fn add_loaded_module(&self, path: PathBuf, module: Value) {
+        self.modules.borrow_mut().insert(path, module);
+    }
+
+    pub fn has_prelude(&self) -> bool {
+        self.prelude.is_some()
+    }
+
+    /// Makes `f` callable from scripts as `name`, replacing any builtin or prelude
+    /// function of that name.
+    /// An `Err` returned by `f` becomes a runtime error in the calling script.
+    pub fn register_fn<F>(&mut self, name: &str, f: F)
+    where
+        F: Fn(&[Value]) -> Result<Value, native::Error> + 'static,
+    {
+        let builtin = Rc::new(Object::Builtin(Rc::new(Builtin {
+            name: name.to_owned(),
+            native: Some(Rc::new(f)),
+        })));
+        let mut host = self.host.borrow_mut();
+        host.removed.remove(name);
+        host.registered.insert(name.to_owned(), builtin);
+    }
+
+    /// Removes the registered builtin, prelude function or default builtin `name`, so
+    /// scripts can no longer call it.
+    pub fn remove_fn(&mut self, name: &str) {
+        let mut host = self.host.borrow_mut();
+        host.registered.remove(name);
+        host.removed.insert(name.to_owned());
+    }
+
+    /// The builtin scripts see as `name`: a registered function first, then a prelude
+    /// function, then a default builtin.
+    pub fn get_builtin(&self, name: &str) -> Option<Rc<Object>> {
+        let host = self.host.borrow();
+        if let Some(builtin) = host.registered.get(name) {
+            return Some(builtin.clone());
+        }
+        if host.removed.contains(name) {
+            return None;
+        }
+        self.prelude
+            .as_ref()
+            .and_then(|prelude| prelude.get(name))
+            .or_else(|| find_builtin(name))
+    }
+
     pub fn get(&self, key: &str) -> Option<Rc<Object>> {
-        let val = self.store.get(key);
+        let val = self.store.borrow().get(key).cloned();
         if val.is_some() {
-            return Some(val.unwrap().clone());
+            return val;
         }
 
         if let Some(outer) = &self.outer {
@@ -38,7 +204,55 @@ impl Environment {
     }
 
     pub fn set(&mut self, key: String, val: Rc<Object>) -> Option<Rc<Object>> {
-        self.store.insert(key, val.clone());
+        self.store.borrow_mut().insert(key, val.clone());
         return Some(val);
     }
 }
+
+// Copies share the scope but never own the captured ones, so that dropping the copy a
+// function holds does not clear them.
+impl Clone for Environment {
+    fn clone(&self) -> Environment {
+        Environment {
+            store: self.store.clone(),
+            outer: self.outer.clone(),
+            prelude: self.prelude.clone(),
+            host: self.host.clone(),
+            modules: self.modules.clone(),
+            duplicate_keys: self.duplicate_keys.clone(),
+            captured: self.captured.clone(),
+            owner: false,
+        }
+    }
+}
+
+// A function holds the scope it was defined in, and that scope usually holds the function,
+// so the cycle is only broken by emptying the scope. Bindings are taken out before they are
+// dropped, since dropping them may drop other environments.
+impl Drop for Environment {
+    fn drop(&mut self) {
+        if !self.owner {
+            return;
+        }
+        let scopes = mem::take(&mut *self.captured.borrow_mut());
+        for scope in scopes.iter().filter_map(Weak::upgrade) {
+            let _bindings = mem::take(&mut *scope.borrow_mut());
+        }
+        let _modules = mem::take(&mut *self.modules.borrow_mut());
+        let _bindings = mem::take(&mut *self.store.borrow_mut());
+    }
+}
+
+// A function keeps the environment it was defined in, and that environment usually
+// holds the function itself, so only the bound names are printed.
+impl fmt::Debug for Environment {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let store = self.store.borrow();
+        let mut keys = store.keys().collect::<Vec<&String>>();
+        keys.sort();
+        f.debug_struct("Environment")
+            .field("store", &keys)
+            .field("outer", &self.outer)
+            .finish()
+    }
+}
@@ -1,19 +1,91 @@
 use crate::ast::Node;
-use crate::environment::Environment;
+use crate::builtins::do_builtin;
+use crate::environment::{DuplicateKeyPolicy, Environment};
+use crate::lexer::Lexer;
 use crate::object;
-use crate::object::{Object, HashPair};
+use crate::object::{EnumType, HashKey, HashPair, Module, Object, StructType, VariantType};
+use crate::ordered_map::OrderedMap;
+use crate::parser::Parser;
+use crate::token::Token;
 use core::borrow::Borrow;
+use std::cell::RefCell;
+use std::fs;
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
 use std::collections::HashMap;
 
+thread_local! {
+    // Names of the functions currently being applied, outermost first.
+    static CALL_STACK: RefCell<Vec<String>> = RefCell::new(vec![]);
+    // The call stack at the point the error now unwinding left its innermost function.
+    static ERROR_STACK: RefCell<Option<Vec<String>>> = RefCell::new(None);
+    // Canonical paths of the files currently being evaluated, outermost first.
+    static MODULE_STACK: RefCell<Vec<PathBuf>> = RefCell::new(vec![]);
+}
+
+/// Evaluates the program in the file at `path`; `import` paths inside it resolve
+/// relative to the file's directory.
+pub fn eval_file(path: &Path, env: &mut Environment) -> Option<Rc<Object>> {
+    let path = match fs::canonicalize(path) {
+        Ok(path) => path,
+        Err(e) => {
+            return Some(Rc::new(Object::Error(format!(
+                "cannot read {}: {}",
+                path.display(),
+                e
+            ))))
+        }
+    };
+    let program = match parse_file(&path) {
+        Ok(program) => program,
+        Err(e) => return Some(e),
+    };
+    return eval_program_in_file(&program, path, env);
+}
+
+// Evaluates an already parsed program as the contents of the file at the canonical `path`.
+pub(crate) fn eval_program_in_file(
+    program: &Box<Node>,
+    path: PathBuf,
+    env: &mut Environment,
+) -> Option<Rc<Object>> {
+    MODULE_STACK.with(|stack| stack.borrow_mut().push(path));
+    let result = eval(program, env);
+    MODULE_STACK.with(|stack| stack.borrow_mut().pop());
+    return result;
+}
+
+fn parse_file(path: &Path) -> Result<Box<Node>, Rc<Object>> {
+    let source = fs::read_to_string(path).map_err(|e| {
+        Rc::new(Object::Error(format!(
+            "cannot read {}: {}",
+            path.display(),
+            e
+        )))
+    })?;
+
+    let mut parser = Parser::new(Lexer::new(source));
+    let program = parser.parse_program();
+    if parser.errors().len() > 0 || program.is_none() {
+        return Err(Rc::new(Object::Error(format!(
+            "parse error in {}: {}",
+            path.display(),
+            parser.errors().join(", ")
+        ))));
+    }
+
+    return Ok(program.unwrap());
+}
+
 pub fn eval(node: &Box<Node>, env: &mut Environment) -> Option<Rc<Object>> {
     let n = node.borrow();
     match n {
         Node::Program {
             statements: statements,
+            span: _,
         } => eval_program(statements, env),
-        Node::Statement { node: node } => eval(node, env),
-        Node::Expression { node: node } => eval(node, env),
+        Node::Statement { node: node, span: _ } => eval(node, env),
+        Node::Expression { node: node, span: _ } => eval(node, env),
         Node::ExpressionStatement {
             token: _,
             expression: expression,
@@ -44,6 +116,7 @@ pub fn eval(node: &Box<Node>, env: &mut Environment) -> Option<Rc<Object>> {
         Node::BlockStatement {
             token: _,
             statements: statements,
+            span: _,
         } => eval_block_statements(statements, env),
         Node::IfExpression {
             token: _,
@@ -63,17 +136,20 @@ pub fn eval(node: &Box<Node>, env: &mut Environment) -> Option<Rc<Object>> {
         Node::Identifier {
             token: _,
             value: value,
+            span: _,
         } => eval_identifier(value, env),
         Node::FunctionLiteral {
             token: _,
             parameters: parameters,
             body: body,
         } => eval_function_literal(parameters, body, env),
-        Node::CallExpression {
+        Node::FunctionDeclaration {
             token: _,
-            function: function,
-            arguments: arguments,
-        } => eval_call_expression(function, arguments, env),
+            name,
+            parameters,
+            body,
+        } => eval_function_declaration(name, parameters, body, env),
+        Node::CallExpression { .. } => eval_postfix_chain(node, env),
         Node::ArrayLiteral {
             token: _,
             elements,
@@ -82,22 +158,84 @@ pub fn eval(node: &Box<Node>, env: &mut Environment) -> Option<Rc<Object>> {
             token: _,
             elements,
         } => eval_hash_literal(elements, env),
-        Node::IndexExpression {
-            token,
-            left,
-            index,
-        } => eval_index_expression(left, index, env),
+        Node::SetLiteral {
+            token: _,
+            elements,
+        } => eval_set_literal(elements, env),
+        Node::IndexExpression { .. }
+        | Node::SliceExpression { .. }
+        | Node::MemberExpression { .. } => eval_postfix_chain(node, env),
+        Node::NullLiteral { token: _ } => Some(Rc::new(object::NULL)),
+        Node::ThrowStatement { token: _, value } => eval_throw_statement(value, env),
+        Node::TryExpression {
+            token: _,
+            block,
+            parameter,
+            handler,
+            finalizer,
+        } => eval_try_expression(block, parameter, handler, finalizer, env),
+        Node::PropagateExpression { token: _, left } => eval_propagate_expression(left, env),
+        Node::StructDeclaration {
+            token: _,
+            name,
+            fields,
+        } => eval_struct_declaration(name, fields, env),
+        Node::StructLiteral {
+            token: _,
+            name,
+            fields,
+            values,
+        } => eval_struct_literal(name, fields, values, env),
+        Node::ImplDeclaration {
+            token: _,
+            name,
+            methods,
+        } => eval_impl_declaration(name, methods, env),
+        Node::ImportStatement {
+            token: _,
+            path,
+            name,
+        } => eval_import_statement(path, name, env),
+        Node::ExportStatement {
+            token: _,
+            statement,
+        } => eval(statement, env),
+        Node::EnumDeclaration {
+            token: _,
+            name,
+            variants,
+        } => eval_enum_declaration(name, variants, env),
+        Node::EnumVariant {
+            token: _,
+            name,
+            fields: _,
+            span: _,
+        } => Some(Rc::new(Object::Error(format!(
+            "enum variant outside of enum declaration: {:?}",
+            name
+        )))),
+        Node::MatchExpression {
+            token: _,
+            subject,
+            patterns,
+            results,
+        } => eval_match_expression(subject, patterns, results, env),
     }
 }
 
 fn eval_program(nodes: &Vec<Box<Node>>, env: &mut Environment) -> Option<Rc<Object>> {
-    let mut result = None;
+    let mut result = Some(Rc::new(object::NULL));
+    hoist_function_declarations(nodes, env);
 
     for node in nodes.iter() {
+        if let Some(name) = function_declaration_name(node) {
+            result = env.get(name);
+            continue;
+        }
         let r = eval(node, env)?;
         if let Object::ReturnValue(v) = r.borrow() {
             return Some(v.clone());
-        } else if let Object::Error(_) = r.borrow() {
+        } else if r.is_error() {
             return Some(r);
         }
         result = Some(r);
@@ -107,14 +245,17 @@ fn eval_program(nodes: &Vec<Box<Node>>, env: &mut Environment) -> Option<Rc<Obje
 }
 
 fn eval_block_statements(nodes: &Vec<Box<Node>>, env: &mut Environment) -> Option<Rc<Object>> {
-    let mut result = None;
+    let mut result = Some(Rc::new(object::NULL));
+    hoist_function_declarations(nodes, env);
 
     for node in nodes.iter() {
+        if let Some(name) = function_declaration_name(node) {
+            result = env.get(name);
+            continue;
+        }
         let r = eval(node, env)?;
 
-        if r.is_same(&Object::ReturnValue(Rc::new(Object::Null)))
-            || r.is_same(&Object::Error(String::new()))
-        {
+        if r.is_return_or_error() {
             return Some(r);
         }
 
@@ -124,7 +265,35 @@ fn eval_block_statements(nodes: &Vec<Box<Node>>, env: &mut Environment) -> Optio
     return result;
 }
 
-fn native_bool_to_bool_object(b: bool) -> Object {
+fn hoist_function_declarations(nodes: &Vec<Box<Node>>, env: &mut Environment) {
+    for node in nodes.iter() {
+        if function_declaration_name(node).is_some() {
+            eval(node, env);
+        }
+    }
+}
+
+fn function_declaration_name(node: &Node) -> Option<&String> {
+    match node {
+        Node::Statement { node, span: _ } => function_declaration_name(node),
+        Node::ExportStatement {
+            token: _,
+            statement,
+        } => function_declaration_name(statement),
+        Node::FunctionDeclaration {
+            token: _,
+            name,
+            parameters: _,
+            body: _,
+        } => match name.borrow() {
+            Node::Identifier { token: _, value, span: _ } => Some(value),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+pub(crate) fn native_bool_to_bool_object(b: bool) -> Object {
     if b {
         object::TRUE
     } else {
@@ -138,9 +307,14 @@ fn eval_prefix_expression(
     env: &mut Environment,
 ) -> Option<Rc<Object>> {
     let right = eval(right, env)?;
-    if right.is_error() {
+    if right.is_return_or_error() {
         return Some(right);
     }
+    if operator == "-" {
+        if let Some(function) = find_protocol_method(&right, "__neg__") {
+            return Some(apply_protocol_method(function, vec![right.clone()]));
+        }
+    }
     match operator {
         "!" => Some(eval_bang_operator_expression(right)),
         "-" => Some(eval_minus_prefix_operator_expression(right)),
@@ -174,15 +348,26 @@ fn eval_infix_expression(
     env: &mut Environment,
 ) -> Option<Rc<Object>> {
     let left = eval(left, env)?;
-    if left.is_error() {
+    if left.is_return_or_error() {
         return Some(left);
     }
 
+    if operator == "??" {
+        if !left.is_null() {
+            return Some(left);
+        }
+        return eval(right, env);
+    }
+
     let right = eval(right, env)?;
-    if right.is_error() {
+    if right.is_return_or_error() {
         return Some(right);
     }
 
+    if let Some(result) = eval_protocol_infix_expression(operator, &left, &right) {
+        return Some(result);
+    }
+
     if left.is_integer() && right.is_integer() {
         if let Object::Integer(n) = left.borrow() {
             if let Object::Integer(m) = right.borrow() {
@@ -199,6 +384,13 @@ fn eval_infix_expression(
         }
     }
 
+    let comparable = left.is_null() || right.is_null() || (left.is_result() && right.is_result());
+    if comparable && (operator == "==" || operator == "!=") {
+        return Some(Rc::new(native_bool_to_bool_object(
+            (left == right) == (operator == "=="),
+        )));
+    }
+
     if !left.is_same(&right) {
         return Some(Rc::new(Object::Error(format!(
             "type mismatch: {:?}, {:?}, {:?}",
@@ -216,6 +408,86 @@ fn eval_infix_expression(
     }))
 }
 
+// Hashes and structs customize operators by defining protocol methods, which are called
+// with both operands. `!=` falls back to `__eq__` and `>` to the right operand's `__lt__`.
+fn eval_protocol_infix_expression(
+    operator: &str,
+    left: &Rc<Object>,
+    right: &Rc<Object>,
+) -> Option<Rc<Object>> {
+    let method = match operator {
+        "+" => "__add__",
+        "-" => "__sub__",
+        "*" => "__mul__",
+        "/" => "__div__",
+        "==" => "__eq__",
+        "!=" => "__ne__",
+        "<" => "__lt__",
+        ">" => "__gt__",
+        _ => return None,
+    };
+
+    if let Some(function) = find_protocol_method(left, method) {
+        return Some(apply_protocol_method(function, vec![left.clone(), right.clone()]));
+    }
+
+    if operator == "!=" {
+        if let Some(function) = find_protocol_method(left, "__eq__") {
+            let eq = apply_protocol_method(function, vec![left.clone(), right.clone()]);
+            if eq.is_return_or_error() {
+                return Some(eq);
+            }
+            return Some(Rc::new(native_bool_to_bool_object(!is_truthy(eq))));
+        }
+    }
+
+    if operator == ">" {
+        if let Some(function) = find_protocol_method(right, "__lt__") {
+            return Some(apply_protocol_method(function, vec![right.clone(), left.clone()]));
+        }
+    }
+
+    return None;
+}
+
+fn find_protocol_method(object: &Object, name: &str) -> Option<Rc<Object>> {
+    match object {
+        Object::Hash(pairs) => hash_member(pairs, name),
+        Object::Struct {
+            struct_type,
+            values: _,
+        } => struct_type.methods.borrow().get(name).cloned(),
+        _ => None,
+    }
+}
+
+fn apply_protocol_method(function: Rc<Object>, arguments: Vec<Rc<Object>>) -> Rc<Object> {
+    match apply_function(function.clone(), arguments) {
+        Some(result) => result,
+        None => Rc::new(Object::Error(format!(
+            "protocol method is not a function: {}",
+            function
+        ))),
+    }
+}
+
+// The text `puts` prints for a value, using its `__str__` method when it has one.
+pub(crate) fn object_to_string(object: &Rc<Object>) -> Result<String, Rc<Object>> {
+    let function = match find_protocol_method(object, "__str__") {
+        Some(function) => function,
+        None => return Ok(object.to_string()),
+    };
+    let result = apply_protocol_method(function, vec![object.clone()]);
+    match result.borrow() {
+        Object::StringValue(s) => Ok(s.to_owned()),
+        _ if result.is_return_or_error() => Err(result),
+        _ => Err(Rc::new(Object::Error(format!(
+            "__str__ must return String, got = {:?}",
+            result
+        )))),
+    }
+}
+
 fn eval_integer_infix_expression(operator: &str, left: i64, right: i64) -> Rc<Object> {
     Rc::new(match operator {
         "+" => Object::Integer(left + right),
@@ -250,7 +522,7 @@ fn eval_if_expression(
     env: &mut Environment,
 ) -> Option<Rc<Object>> {
     let condition = eval(condition, env)?;
-    if condition.is_error() {
+    if condition.is_return_or_error() {
         return Some(condition);
     }
 
@@ -264,7 +536,7 @@ fn eval_if_expression(
     }
 }
 
-fn is_truthy(object: Rc<Object>) -> bool {
+pub(crate) fn is_truthy(object: Rc<Object>) -> bool {
     match object.borrow() {
         Object::Null => false,
         Object::Bool(true) => true,
@@ -275,23 +547,150 @@ fn is_truthy(object: Rc<Object>) -> bool {
 
 fn eval_return_statement(return_value: &Box<Node>, env: &mut Environment) -> Option<Rc<Object>> {
     let val = eval(return_value, env)?;
-    if val.is_error() {
+    if val.is_return_or_error() {
         return Some(val);
     }
     return Some(Rc::new(Object::ReturnValue(val)));
 }
 
+fn eval_throw_statement(value: &Box<Node>, env: &mut Environment) -> Option<Rc<Object>> {
+    let val = eval(value, env)?;
+    if val.is_return_or_error() {
+        return Some(val);
+    }
+    return Some(Rc::new(Object::Thrown(val)));
+}
+
+fn eval_try_expression(
+    block: &Box<Node>,
+    parameter: &Option<Box<Node>>,
+    handler: &Option<Box<Node>>,
+    finalizer: &Option<Box<Node>>,
+    env: &mut Environment,
+) -> Option<Rc<Object>> {
+    ERROR_STACK.with(|error_stack| error_stack.borrow_mut().take());
+
+    let mut result = eval(block, env)?;
+
+    if result.is_error() && handler.is_some() {
+        let stack = ERROR_STACK
+            .with(|error_stack| error_stack.borrow_mut().take())
+            .unwrap_or_else(current_call_stack);
+        let mut handler_env = Environment::new_enclosed(Rc::new(env.clone()));
+        if let Some(Node::Identifier { token: _, value, span: _ }) = parameter.as_ref().map(|p| p.borrow()) {
+            handler_env.set(value.to_owned(), error_to_hash(&result, stack));
+        }
+        let handled = eval(handler.as_ref().unwrap(), &mut handler_env);
+        handler_env.close();
+        result = handled?;
+    }
+
+    if let Some(finalizer) = finalizer {
+        let error_stack = ERROR_STACK.with(|error_stack| error_stack.borrow_mut().take());
+        let r = eval(finalizer, env)?;
+        if r.is_return_or_error() {
+            return Some(r);
+        }
+        ERROR_STACK.with(|s| *s.borrow_mut() = error_stack);
+    }
+
+    return Some(result);
+}
+
+fn current_call_stack() -> Vec<String> {
+    CALL_STACK.with(|stack| stack.borrow().iter().rev().cloned().collect())
+}
+
+// Runtime errors and thrown values are both caught as a hash with `message`, `kind` and
+// `stack` (innermost function first). A thrown hash may provide its own message and kind,
+// and any thrown value is also available as `value`.
+fn error_to_hash(error: &Rc<Object>, stack: Vec<String>) -> Rc<Object> {
+    let stack = Rc::new(Object::Array(
+        stack
+            .into_iter()
+            .map(|name| Rc::new(Object::StringValue(name)))
+            .collect(),
+    ));
+    match error.borrow() {
+        Object::Thrown(value) => {
+            let (message, kind) = match value.borrow() {
+                Object::Hash(pairs) => (
+                    hash_member(pairs, "message"),
+                    hash_member(pairs, "kind"),
+                ),
+                _ => (None, None),
+            };
+            let message =
+                message.unwrap_or_else(|| Rc::new(Object::StringValue(value.to_string())));
+            let kind = kind.unwrap_or_else(|| Rc::new(Object::StringValue("Error".to_owned())));
+            new_string_hash(vec![
+                ("message", message),
+                ("kind", kind),
+                ("stack", stack),
+                ("value", value.clone()),
+            ])
+        }
+        Object::Error(message) => new_string_hash(vec![
+            ("message", Rc::new(Object::StringValue(message.to_owned()))),
+            ("kind", Rc::new(Object::StringValue(error_kind(message).to_owned()))),
+            ("stack", stack),
+        ]),
+        _ => error.clone(),
+    }
+}
+
+fn error_kind(message: &str) -> &'static str {
+    if message.starts_with("type mismatch") || message.starts_with("unknown operator") {
+        "TypeError"
+    } else if message.starts_with("identifier not found") {
+        "NameError"
+    } else if message.starts_with("unusable as hash key") {
+        "TypeError"
+    } else if message.starts_with("invalid index") || message.starts_with("index operator") {
+        "IndexError"
+    } else if message.starts_with("wrong number of arguments") || message.starts_with("argument to") {
+        "ArgumentError"
+    } else {
+        "RuntimeError"
+    }
+}
+
+fn new_string_hash(entries: Vec<(&str, Rc<Object>)>) -> Rc<Object> {
+    let mut pairs = OrderedMap::<HashKey, HashPair>::new();
+    for (key, value) in entries {
+        let key = Rc::new(Object::StringValue(key.to_owned()));
+        pairs.insert(key.create_hash_key().unwrap(), HashPair { key, value });
+    }
+    return Rc::new(Object::Hash(pairs));
+}
+
+// `err(e)?` leaves the enclosing function the same way `return err(e);` does.
+fn eval_propagate_expression(left: &Box<Node>, env: &mut Environment) -> Option<Rc<Object>> {
+    let left = eval(left, env)?;
+    if left.is_return_or_error() {
+        return Some(left);
+    }
+    match left.borrow() {
+        Object::Ok(v) => Some(v.clone()),
+        Object::Err(_) => Some(Rc::new(Object::ReturnValue(left.clone()))),
+        _ => Some(Rc::new(Object::Error(format!(
+            "? operator not supported: {:?}",
+            left
+        )))),
+    }
+}
+
 fn eval_let_statement(
     name: &Box<Node>,
     value: &Box<Node>,
     env: &mut Environment,
 ) -> Option<Rc<Object>> {
     let val = eval(value, env)?;
-    if val.is_error() {
+    if val.is_return_or_error() {
         return Some(val);
     }
 
-    if let Node::Identifier { token: _, value: v } = name.borrow() {
+    if let Node::Identifier { token: _, value: v, span: _ } = name.borrow() {
         let val = env.set(v.to_owned(), val);
         return val;
     } else {
@@ -302,12 +701,474 @@ fn eval_let_statement(
     }
 }
 
+fn eval_struct_declaration(
+    name: &Box<Node>,
+    fields: &Vec<Box<Node>>,
+    env: &mut Environment,
+) -> Option<Rc<Object>> {
+    let mut field_names: Vec<String> = vec![];
+    for field in fields.iter() {
+        if let Node::Identifier { token: _, value, span: _ } = field.borrow() {
+            if field_names.contains(value) {
+                return Some(Rc::new(Object::Error(format!(
+                    "duplicate field: {}",
+                    value
+                ))));
+            }
+            field_names.push(value.to_owned());
+        }
+    }
+
+    if let Node::Identifier { token: _, value: v, span: _ } = name.borrow() {
+        let struct_type = Rc::new(Object::StructType(Rc::new(StructType {
+            name: v.to_owned(),
+            fields: field_names,
+            methods: RefCell::new(HashMap::new()),
+        })));
+        return env.set(v.to_owned(), struct_type);
+    } else {
+        return Some(Rc::new(Object::Error(format!(
+            "invalid identifier: {:?}",
+            name
+        ))));
+    }
+}
+
+fn eval_struct_literal(
+    name: &Box<Node>,
+    fields: &Vec<Box<Node>>,
+    values: &Vec<Box<Node>>,
+    env: &mut Environment,
+) -> Option<Rc<Object>> {
+    let struct_type = eval(name, env)?;
+    if struct_type.is_return_or_error() {
+        return Some(struct_type);
+    }
+    let struct_type = match struct_type.borrow() {
+        Object::StructType(struct_type) => struct_type.clone(),
+        _ => {
+            return Some(Rc::new(Object::Error(format!(
+                "not a struct: {:?}",
+                struct_type
+            ))))
+        }
+    };
+
+    let mut struct_values: Vec<Option<Rc<Object>>> = vec![None; struct_type.fields.len()];
+    for (field, value) in fields.iter().zip(values.iter()) {
+        let field = match field.borrow() {
+            Node::Identifier { token: _, value, span: _ } => value,
+            _ => continue,
+        };
+        let i = match struct_type.field_index(field) {
+            Some(i) => i,
+            None => {
+                return Some(Rc::new(Object::Error(format!(
+                    "unknown field: {} for {}",
+                    field, struct_type.name
+                ))))
+            }
+        };
+        if struct_values[i].is_some() {
+            return Some(Rc::new(Object::Error(format!(
+                "duplicate field: {}",
+                field
+            ))));
+        }
+        let value = eval(value, env)?;
+        if value.is_return_or_error() {
+            return Some(value);
+        }
+        struct_values[i] = Some(value);
+    }
+
+    let mut values = vec![];
+    for (i, value) in struct_values.into_iter().enumerate() {
+        match value {
+            Some(value) => values.push(value),
+            None => {
+                return Some(Rc::new(Object::Error(format!(
+                    "missing field: {} for {}",
+                    struct_type.fields[i], struct_type.name
+                ))))
+            }
+        }
+    }
+
+    return Some(Rc::new(Object::Struct {
+        struct_type,
+        values,
+    }));
+}
+
+fn eval_impl_declaration(
+    name: &Box<Node>,
+    methods: &Vec<Box<Node>>,
+    env: &mut Environment,
+) -> Option<Rc<Object>> {
+    let target = eval(name, env)?;
+    if target.is_return_or_error() {
+        return Some(target);
+    }
+    let struct_type = match target.borrow() {
+        Object::StructType(struct_type) => struct_type.clone(),
+        _ => {
+            return Some(Rc::new(Object::Error(format!(
+                "impl target must be a struct, got = {}",
+                target
+            ))))
+        }
+    };
+
+    for method in methods.iter() {
+        if let Node::FunctionDeclaration {
+            token: _,
+            name,
+            parameters,
+            body,
+        } = method.borrow()
+        {
+            let method_name = match identifier_name(name) {
+                Some(method_name) => method_name,
+                None => return Some(invalid_identifier(name)),
+            };
+            let function = Rc::new(Object::Function {
+                name: Some(format!("{}.{}", struct_type.name, method_name)),
+                parameters: parameters.clone(),
+                body: body.clone(),
+                environment: env.clone(),
+            });
+            struct_type
+                .methods
+                .borrow_mut()
+                .insert(method_name.to_owned(), function);
+        }
+    }
+
+    return Some(target);
+}
+
+fn eval_import_statement(
+    path: &Box<Node>,
+    name: &Box<Node>,
+    env: &mut Environment,
+) -> Option<Rc<Object>> {
+    let path = match path.borrow() {
+        Node::StringLiteral { token: _, value } => value,
+        _ => {
+            return Some(Rc::new(Object::Error(format!(
+                "import path must be a string literal, got = {:?}",
+                path
+            ))))
+        }
+    };
+    let importer = MODULE_STACK.with(|stack| stack.borrow().last().cloned());
+    let path = match importer.as_ref().and_then(|p| p.parent()) {
+        Some(dir) => dir.join(path),
+        None => PathBuf::from(path),
+    };
+
+    let module = eval_module(&path, env);
+    if module.is_error() {
+        return Some(module);
+    }
+
+    match identifier_name(name) {
+        Some(name) => env.set(name.to_owned(), module),
+        None => Some(invalid_identifier(name)),
+    }
+}
+
+// Each module is evaluated once, in its own Environment, and its exported names are
+// collected into a Module that every later import of the same file shares.
+fn eval_module(path: &Path, importer: &Environment) -> Rc<Object> {
+    let path = match fs::canonicalize(path) {
+        Ok(path) => path,
+        Err(e) => {
+            return Rc::new(Object::Error(format!(
+                "cannot import {}: {}",
+                path.display(),
+                e
+            )))
+        }
+    };
+
+    if let Some(module) = importer.loaded_module(&path) {
+        return module;
+    }
+
+    let cycle = MODULE_STACK.with(|stack| {
+        let stack = stack.borrow();
+        stack.iter().position(|p| *p == path).map(|i| {
+            stack[i..]
+                .iter()
+                .chain(Some(&path))
+                .map(|p| p.display().to_string())
+                .collect::<Vec<String>>()
+        })
+    });
+    if let Some(cycle) = cycle {
+        return Rc::new(Object::Error(format!("import cycle: {}", cycle.join(" -> "))));
+    }
+
+    let program = match parse_file(&path) {
+        Ok(program) => program,
+        Err(e) => return e,
+    };
+
+    let mut env = importer.new_module();
+    MODULE_STACK.with(|stack| stack.borrow_mut().push(path.clone()));
+    let result = eval(&program, &mut env);
+    MODULE_STACK.with(|stack| stack.borrow_mut().pop());
+    if let Some(result) = result {
+        if result.is_error() {
+            return result;
+        }
+    }
+
+    let mut exports = OrderedMap::new();
+    if let Node::Program { statements, span: _ } = program.borrow() {
+        for statement in statements.iter() {
+            if let Some(name) = exported_name(statement) {
+                exports.insert(name.to_owned(), env.get(name).unwrap_or(Rc::new(object::NULL)));
+            }
+        }
+    }
+
+    let module = Rc::new(Object::Module(Rc::new(Module {
+        path: path.clone(),
+        exports,
+    })));
+    importer.add_loaded_module(path, module.clone());
+    return module;
+}
+
+fn exported_name(node: &Node) -> Option<&String> {
+    match node {
+        Node::Statement { node, span: _ } => exported_name(node),
+        Node::ExportStatement {
+            token: _,
+            statement,
+        } => match statement.borrow() {
+            Node::LetStatement {
+                token: _,
+                name,
+                value: _,
+            } => identifier_name(name),
+            _ => function_declaration_name(statement),
+        },
+        _ => None,
+    }
+}
+
+fn eval_enum_declaration(
+    name: &Box<Node>,
+    variants: &Vec<Box<Node>>,
+    env: &mut Environment,
+) -> Option<Rc<Object>> {
+    let enum_name = match identifier_name(name) {
+        Some(enum_name) => enum_name,
+        None => return Some(invalid_identifier(name)),
+    };
+
+    let mut variant_types: Vec<Rc<VariantType>> = vec![];
+    for variant in variants.iter() {
+        if let Node::EnumVariant {
+            token: _,
+            name,
+            fields,
+            span: _,
+        } = variant.borrow()
+        {
+            let tag = match identifier_name(name) {
+                Some(tag) => tag,
+                None => return Some(invalid_identifier(name)),
+            };
+            if variant_types.iter().any(|v| v.tag == *tag) {
+                return Some(Rc::new(Object::Error(format!(
+                    "duplicate variant: {}",
+                    tag
+                ))));
+            }
+            variant_types.push(Rc::new(VariantType {
+                enum_name: enum_name.to_owned(),
+                tag: tag.to_owned(),
+                fields: fields
+                    .iter()
+                    .filter_map(|f| identifier_name(f).cloned())
+                    .collect(),
+            }));
+        }
+    }
+
+    for variant_type in variant_types.iter() {
+        env.set(variant_type.tag.to_owned(), variant_value(variant_type));
+    }
+
+    let enum_type = Rc::new(Object::EnumType(Rc::new(EnumType {
+        name: enum_name.to_owned(),
+        variants: variant_types,
+    })));
+    return env.set(enum_name.to_owned(), enum_type);
+}
+
+// A variant without fields is a value of its own; any other variant is its constructor.
+fn variant_value(variant_type: &Rc<VariantType>) -> Rc<Object> {
+    if variant_type.fields.is_empty() {
+        Rc::new(Object::Variant {
+            variant_type: variant_type.clone(),
+            payload: vec![],
+        })
+    } else {
+        Rc::new(Object::VariantType(variant_type.clone()))
+    }
+}
+
+fn eval_match_expression(
+    subject: &Box<Node>,
+    patterns: &Vec<Box<Node>>,
+    results: &Vec<Box<Node>>,
+    env: &mut Environment,
+) -> Option<Rc<Object>> {
+    let subject = eval(subject, env)?;
+    if subject.is_return_or_error() {
+        return Some(subject);
+    }
+
+    for (pattern, result) in patterns.iter().zip(results.iter()) {
+        let mut bindings = vec![];
+        match match_pattern(pattern, &subject, &mut bindings, env) {
+            Ok(true) => {
+                let mut arm_env = Environment::new_enclosed(Rc::new(env.clone()));
+                for (name, value) in bindings {
+                    arm_env.set(name, value);
+                }
+                let result = eval(result, &mut arm_env);
+                arm_env.close();
+                return result;
+            }
+            Ok(false) => continue,
+            Err(error) => return Some(error),
+        }
+    }
+
+    return Some(Rc::new(Object::Error(format!(
+        "no match arm for: {}",
+        subject
+    ))));
+}
+
+// Patterns are expressions: `_` matches anything, a lowercase identifier binds the value,
+// `Constructor(p, ...)` destructures a variant or struct, a bare variant constructor
+// matches that variant with any payload, and anything else must equal the value.
+fn match_pattern(
+    pattern: &Box<Node>,
+    value: &Rc<Object>,
+    bindings: &mut Vec<(String, Rc<Object>)>,
+    env: &mut Environment,
+) -> Result<bool, Rc<Object>> {
+    match pattern.borrow() {
+        Node::Expression { node, span: _ } => match_pattern(node, value, bindings, env),
+        Node::Identifier { token: _, value: name, span: _ } if name == "_" => Ok(true),
+        Node::Identifier { token: _, value: name, span: _ } if !is_type_name(name) => {
+            bindings.push((name.to_owned(), value.clone()));
+            Ok(true)
+        }
+        Node::CallExpression {
+            token: _,
+            function,
+            arguments,
+        } => {
+            let constructor = eval_pattern_value(function, env)?;
+            let fields = match (constructor.borrow(), value.borrow()) {
+                (
+                    Object::VariantType(expected),
+                    Object::Variant {
+                        variant_type,
+                        payload,
+                    },
+                ) if expected.enum_name == variant_type.enum_name
+                    && expected.tag == variant_type.tag =>
+                {
+                    payload
+                }
+                (
+                    Object::StructType(expected),
+                    Object::Struct {
+                        struct_type,
+                        values,
+                    },
+                ) if Rc::ptr_eq(expected, struct_type) => values,
+                (Object::VariantType(_), _) | (Object::StructType(_), _) => return Ok(false),
+                _ => {
+                    return Err(Rc::new(Object::Error(format!(
+                        "invalid pattern: {}",
+                        constructor
+                    ))))
+                }
+            };
+            if fields.len() != arguments.len() {
+                return Ok(false);
+            }
+            for (argument, field) in arguments.iter().zip(fields.iter()) {
+                if !match_pattern(argument, field, bindings, env)? {
+                    return Ok(false);
+                }
+            }
+            Ok(true)
+        }
+        _ => {
+            let expected = eval_pattern_value(pattern, env)?;
+            match (expected.borrow(), value.borrow()) {
+                (
+                    Object::VariantType(expected),
+                    Object::Variant {
+                        variant_type,
+                        payload: _,
+                    },
+                ) => Ok(expected.enum_name == variant_type.enum_name
+                    && expected.tag == variant_type.tag),
+                _ => Ok(expected == *value),
+            }
+        }
+    }
+}
+
+fn eval_pattern_value(
+    pattern: &Box<Node>,
+    env: &mut Environment,
+) -> Result<Rc<Object>, Rc<Object>> {
+    match eval(pattern, env) {
+        Some(value) if value.is_return_or_error() => Err(value),
+        Some(value) => Ok(value),
+        None => Err(Rc::new(Object::Error(format!(
+            "invalid pattern: {:?}",
+            pattern
+        )))),
+    }
+}
+
+fn is_type_name(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_uppercase())
+}
+
+fn identifier_name(node: &Node) -> Option<&String> {
+    match node {
+        Node::Identifier { token: _, value, span: _ } => Some(value),
+        _ => None,
+    }
+}
+
+fn invalid_identifier(node: &Node) -> Rc<Object> {
+    Rc::new(Object::Error(format!("invalid identifier: {:?}", node)))
+}
+
 fn eval_identifier(value: &String, env: &mut Environment) -> Option<Rc<Object>> {
     let mut object = env.get(value);
 
     if object.is_none() {
 
-        object = find_builtin(value);
+        object = env.get_builtin(value);
 
         if object.is_none() {
             return Some(Rc::new(Object::Error(format!(
@@ -325,31 +1186,222 @@ fn eval_function_literal(
     env: &mut Environment,
 ) -> Option<Rc<Object>> {
     Some(Rc::new(Object::Function {
+        name: None,
         parameters: parameters.clone(),
         body: body.clone(),
         environment: env.clone(),
     }))
 }
 
+fn eval_function_declaration(
+    name: &Box<Node>,
+    parameters: &Vec<Box<Node>>,
+    body: &Box<Node>,
+    env: &mut Environment,
+) -> Option<Rc<Object>> {
+    if let Node::Identifier { token: _, value: v, span: _ } = name.borrow() {
+        let function = Rc::new(Object::Function {
+            name: Some(v.to_owned()),
+            parameters: parameters.clone(),
+            body: body.clone(),
+            environment: env.clone(),
+        });
+        return env.set(v.to_owned(), function);
+    } else {
+        return Some(Rc::new(Object::Error(format!(
+            "invalid identifier: {:?}",
+            name
+        ))));
+    }
+}
+
+// One link of a chain of calls, indexes, slices and member accesses such as `a?.b.c(d)`.
+// Once an optional link finds null the rest of the chain is skipped, and the whole
+// chain is null.
+enum Chain {
+    Value(Rc<Object>),
+    Skipped,
+}
+
+fn eval_postfix_chain(node: &Box<Node>, env: &mut Environment) -> Option<Rc<Object>> {
+    match eval_chain(node, env)? {
+        Chain::Value(value) => Some(value),
+        Chain::Skipped => Some(Rc::new(object::NULL)),
+    }
+}
+
+fn eval_chain(node: &Box<Node>, env: &mut Environment) -> Option<Chain> {
+    let value = match node.borrow() {
+        Node::Expression { node, span: _ } => return eval_chain(node, env),
+        Node::CallExpression {
+            token: _,
+            function,
+            arguments,
+        } => match member_expression_parts(function) {
+            Some((token, left, name)) => {
+                let receiver = match eval_chain_operand(left, *token == Token::OptionalDot, env)? {
+                    Ok(receiver) => receiver,
+                    Err(chain) => return Some(chain),
+                };
+                eval_method_call(receiver, name, arguments, env)
+            }
+            None => {
+                let function = match eval_chain_operand(function, false, env)? {
+                    Ok(function) => function,
+                    Err(chain) => return Some(chain),
+                };
+                eval_call_expression(function, arguments, env)
+            }
+        },
+        Node::IndexExpression { token, left, index } => {
+            let left = match eval_chain_operand(left, *token == Token::OptionalDot, env)? {
+                Ok(left) => left,
+                Err(chain) => return Some(chain),
+            };
+            eval_index_expression(left, index, env)
+        }
+        Node::SliceExpression {
+            token: _,
+            left,
+            start,
+            end,
+        } => {
+            let left = match eval_chain_operand(left, false, env)? {
+                Ok(left) => left,
+                Err(chain) => return Some(chain),
+            };
+            eval_slice_expression(left, start, end, env)
+        }
+        Node::MemberExpression {
+            token,
+            left,
+            member,
+        } => {
+            let left = match eval_chain_operand(left, *token == Token::OptionalDot, env)? {
+                Ok(left) => left,
+                Err(chain) => return Some(chain),
+            };
+            eval_member_expression(left, member)
+        }
+        _ => eval(node, env),
+    };
+    value.map(Chain::Value)
+}
+
+// The value the next link applies to, or `Err` with what the chain ends with: an error or
+// return value from evaluating `node`, or `Skipped` once an optional link has found null.
+fn eval_chain_operand(
+    node: &Box<Node>,
+    optional: bool,
+    env: &mut Environment,
+) -> Option<Result<Rc<Object>, Chain>> {
+    let value = match eval_chain(node, env)? {
+        Chain::Value(value) => value,
+        Chain::Skipped => return Some(Err(Chain::Skipped)),
+    };
+    if value.is_return_or_error() {
+        return Some(Err(Chain::Value(value)));
+    }
+    if optional {
+        // The lexer reads `r?.x` as an optional link, but on a result it means `(r?).x`:
+        // unwrap an `Ok`, or return the `Err` from the enclosing function.
+        match value.borrow() {
+            Object::Null => return Some(Err(Chain::Skipped)),
+            Object::Ok(v) => return Some(Ok(v.clone())),
+            Object::Err(_) => return Some(Err(Chain::Value(Rc::new(Object::ReturnValue(value))))),
+            _ => {}
+        }
+    }
+    Some(Ok(value))
+}
+
 fn eval_call_expression(
-    function: &Box<Node>,
+    function: Rc<Object>,
     arguments: &Vec<Box<Node>>,
     env: &mut Environment,
 ) -> Option<Rc<Object>> {
-    let function = eval(function, env)?;
+    let arguments = eval_expression(arguments, env)?;
+    if arguments.len() == 1 && arguments[0].is_return_or_error() {
+        return Some(arguments[0].clone());
+    }
 
-    if function.is_error() {
-        return Some(function);
+    return apply_function(function, arguments);
+}
+
+fn member_expression_parts(node: &Node) -> Option<(&Token, &Box<Node>, &String)> {
+    match node {
+        Node::Expression { node, span: _ } => member_expression_parts(node),
+        Node::MemberExpression {
+            token,
+            left,
+            member,
+        } => match member.borrow() {
+            Node::Identifier { token: _, value, span: _ } => Some((token, left, value)),
+            _ => None,
+        },
+        _ => None,
     }
+}
 
+// `receiver.name(args)` calls a function stored under `name` when the receiver is a
+// hash holding one, and otherwise the builtin `name` with the receiver as first argument.
+fn eval_method_call(
+    receiver: Rc<Object>,
+    name: &str,
+    arguments: &Vec<Box<Node>>,
+    env: &mut Environment,
+) -> Option<Rc<Object>> {
     let arguments = eval_expression(arguments, env)?;
-    if arguments.len() == 1 {
-        if let Object::Error(e) = arguments[0].borrow() {
-            return Some(Rc::new(Object::Error(e.to_string())));
+    if arguments.len() == 1 && arguments[0].is_return_or_error() {
+        return Some(arguments[0].clone());
+    }
+
+    if let Object::Hash(pairs) = receiver.borrow() {
+        if let Some(function) = hash_member(pairs, name) {
+            return apply_function(function, arguments);
         }
     }
 
-    return apply_function(function, arguments);
+    if let Object::Struct {
+        struct_type,
+        values,
+    } = receiver.borrow()
+    {
+        if let Some(i) = struct_type.field_index(name) {
+            return apply_function(values[i].clone(), arguments);
+        }
+        let method = struct_type.methods.borrow().get(name).cloned();
+        if let Some(method) = method {
+            let mut args = vec![receiver.clone()];
+            args.extend(arguments);
+            return apply_function(method, args);
+        }
+    }
+
+    if let Object::EnumType(enum_type) = receiver.borrow() {
+        if let Some(variant_type) = enum_type.variants.iter().find(|v| v.tag == name) {
+            return apply_function(variant_value(variant_type), arguments);
+        }
+    }
+
+    if let Object::Module(module) = receiver.borrow() {
+        let function = module_export(module, name);
+        if function.is_error() {
+            return Some(function);
+        }
+        return apply_function(function, arguments);
+    }
+
+    if let Some(builtin) = env.get_builtin(name) {
+        let mut args = vec![receiver];
+        args.extend(arguments);
+        return apply_function(builtin, args);
+    }
+
+    return Some(Rc::new(Object::Error(format!(
+        "unknown method: {}",
+        name
+    ))));
 }
 
 fn eval_expression(expressions: &Vec<Box<Node>>, env: &mut Environment) -> Option<Vec<Rc<Object>>> {
@@ -357,8 +1409,8 @@ fn eval_expression(expressions: &Vec<Box<Node>>, env: &mut Environment) -> Optio
 
     for expression in expressions.iter() {
         let result = eval(expression, env)?;
-        if result.is_error() {
-            return None;
+        if result.is_return_or_error() {
+            return Some(vec![result]);
         }
         results.push(result);
     }
@@ -366,12 +1418,46 @@ fn eval_expression(expressions: &Vec<Box<Node>>, env: &mut Environment) -> Optio
     return Some(results);
 }
 
-fn apply_function(function: Rc<Object>, argument: Vec<Rc<Object>>) -> Option<Rc<Object>> {
-    if let Object::Builtin(name) = function.borrow() {
-        return do_builtin(name, argument);
+pub(crate) fn apply_function(function: Rc<Object>, argument: Vec<Rc<Object>>) -> Option<Rc<Object>> {
+    if let Object::Builtin(builtin) = function.borrow() {
+        return match &builtin.native {
+            Some(native) => Some(native(&argument).unwrap_or_else(|e| {
+                Rc::new(Object::Error(e.message_in(&builtin.name)))
+            })),
+            None => do_builtin(&builtin.name, argument),
+        };
+    }
+
+    if let Object::VariantType(variant_type) = function.borrow() {
+        if argument.len() != variant_type.fields.len() {
+            return Some(Rc::new(Object::Error(format!(
+                "wrong number of arguments, got = {}, want = {}",
+                argument.len(),
+                variant_type.fields.len()
+            ))));
+        }
+        return Some(Rc::new(Object::Variant {
+            variant_type: variant_type.clone(),
+            payload: argument,
+        }));
+    }
+
+    if let Object::StructType(struct_type) = function.borrow() {
+        if argument.len() != struct_type.fields.len() {
+            return Some(Rc::new(Object::Error(format!(
+                "wrong number of arguments, got = {}, want = {}",
+                argument.len(),
+                struct_type.fields.len()
+            ))));
+        }
+        return Some(Rc::new(Object::Struct {
+            struct_type: struct_type.clone(),
+            values: argument,
+        }));
     }
 
     if let Object::Function {
+        name,
         parameters: p,
         body: b,
         environment: e,
@@ -383,6 +1469,7 @@ fn apply_function(function: Rc<Object>, argument: Vec<Rc<Object>>) -> Option<Rc<
             if let Node::Identifier {
                 token: _,
                 value: key,
+                span: _,
             } = v
             {
                 let a = argument.get(i);
@@ -393,10 +1480,18 @@ fn apply_function(function: Rc<Object>, argument: Vec<Rc<Object>>) -> Option<Rc<
             }
         }
 
-        println!("[apply_function] extended_env is {:?}", extended_env);
-        println!("[apply_function] body is {:?}", b);
-
+        let name = name.to_owned().unwrap_or_else(|| "<anonymous>".to_owned());
+        CALL_STACK.with(|stack| stack.borrow_mut().push(name));
         let result = eval(&b, &mut extended_env);
+        extended_env.close();
+        if result.as_ref().filter(|r| r.is_error()).is_some() {
+            ERROR_STACK.with(|error_stack| {
+                if error_stack.borrow().is_none() {
+                    *error_stack.borrow_mut() = Some(current_call_stack());
+                }
+            });
+        }
+        CALL_STACK.with(|stack| stack.borrow_mut().pop());
 
         if let Some(s) = result.borrow() {
             if let Object::ReturnValue(v) = s.borrow() {
@@ -407,73 +1502,112 @@ fn apply_function(function: Rc<Object>, argument: Vec<Rc<Object>>) -> Option<Rc<
         return result;
     }
 
-    return None;
+    return Some(Rc::new(Object::Error(format!(
+        "not a function: {}",
+        function.type_name()
+    ))));
 }
 
 fn eval_array_literal(elements: &Vec<Box<Node>>, env: &mut Environment) -> Option<Rc<Object>> {
     let elements = eval_expression(elements, env)?;
-    if elements.len() == 1 && elements[0].is_error() {
+    if elements.len() == 1 && elements[0].is_return_or_error() {
         return Some(elements[0].clone());
     }
     return Some(Rc::new(Object::Array(elements)));
 }
 
 fn eval_hash_literal(elements: &Vec<Box<Node>>, env: &mut Environment) -> Option<Rc<Object>> {
-    let mut hash_object_value = HashMap::<String, HashPair>::new();
-
-    let mut iter = elements.chunks(2);
+    let mut hash_object_value = OrderedMap::<HashKey, HashPair>::new();
 
-    for chunk in iter.next() {
+    for chunk in elements.chunks(2) {
         let (key, value) = (chunk[0].clone(), chunk[1].clone());
         let key = eval(&key, env)?;
-        if key.is_error() {
+        if key.is_return_or_error() {
             return Some(key);
         }
         let value = eval(&value, env)?;
-        if value.is_error() {
+        if value.is_return_or_error() {
             return Some(value);
         }
-        let key = key.create_hash_key()?;
-        hash_object_value.insert(key.to_owned(), HashPair{ key, value });
+        let hash_key = match key.create_hash_key() {
+            Some(hash_key) => hash_key,
+            None => return Some(unusable_hash_key(&key)),
+        };
+        if hash_object_value.contains_key(&hash_key)
+            && env.duplicate_key_policy() == DuplicateKeyPolicy::Error
+        {
+            return Some(Rc::new(Object::Error(format!(
+                "duplicate key in hash literal: {}",
+                key
+            ))));
+        }
+        hash_object_value.insert(hash_key, HashPair{ key, value });
     }
 
     return Some(Rc::new(Object::Hash(hash_object_value)));
 }
 
+// Set elements follow the same rules as hash keys; repeated elements are kept once.
+fn eval_set_literal(elements: &Vec<Box<Node>>, env: &mut Environment) -> Option<Rc<Object>> {
+    let elements = eval_expression(elements, env)?;
+    if elements.len() == 1 && elements[0].is_return_or_error() {
+        return Some(elements[0].clone());
+    }
+
+    let mut set = OrderedMap::<HashKey, Rc<Object>>::new();
+    for e in elements.into_iter() {
+        match e.create_hash_key() {
+            Some(key) => set.insert(key, e),
+            None => return Some(unusable_hash_key(&e)),
+        };
+    }
+
+    return Some(Rc::new(Object::Set(set)));
+}
+
+pub(crate) fn unusable_hash_key(key: &Rc<Object>) -> Rc<Object> {
+    Rc::new(Object::Error(format!(
+        "unusable as hash key: {}",
+        key.type_name()
+    )))
+}
+
 fn eval_index_expression(
-    left: &Box<Node>,
+    left: Rc<Object>,
     index: &Box<Node>,
     env: &mut Environment,
 ) -> Option<Rc<Object>> {
-    let left = eval(left, env)?;
-    if left.is_error() {
-        return Some(left);
-    }
     let index = eval(index, env)?;
-    if index.is_error() {
+    if index.is_return_or_error() {
         return Some(index);
     }
+    if let Some(function) = find_protocol_method(&left, "__index__") {
+        return Some(apply_protocol_method(function, vec![left.clone(), index.clone()]));
+    }
     if let Object::Array(elements) = left.borrow() {
         if let Object::Integer(i) = index.borrow() {
-            let max = elements.len() - 1;
-            let i = *i as usize;
-            if i < 0 || max < i {
-                return Some(Rc::new(Object::Error(format!(
-                    "invalid index: {}",
-                    i
-                ))));
-            }
-            return Some(elements[i].clone());
+            return match resolve_index(elements.len(), *i) {
+                Some(i) => Some(elements[i].clone()),
+                None => Some(Rc::new(Object::Error(format!("invalid index: {}", i)))),
+            };
         }
-    } else if let Object::Hash(elements) = left.borrow() {
-        let key = index.create_hash_key();
-        if key.is_none() {
-            Some(Rc::new(Object::Error(format!(
-                "invalid index: {:?}", index))));
+    } else if let Object::StringValue(s) = left.borrow() {
+        if let Object::Integer(i) = index.borrow() {
+            let c = resolve_index(s.chars().count(), *i).and_then(|i| s.chars().nth(i));
+            return match c {
+                Some(c) => Some(Rc::new(Object::StringValue(c.to_string()))),
+                None => Some(Rc::new(Object::Error(format!("invalid index: {}", i)))),
+            };
         }
-        let key = key.unwrap();
-        let value = elements.get(&key)?;
-        return Some(value.value.clone());
+    } else if let Object::Hash(elements) = left.borrow() {
+        let key = match index.create_hash_key() {
+            Some(key) => key,
+            None => return Some(unusable_hash_key(&index)),
+        };
+        return match elements.get(&key) {
+            Some(pair) => Some(pair.value.clone()),
+            None => Some(Rc::new(object::NULL)),
+        };
     }
     return Some(Rc::new(Object::Error(format!(
         "index operator not supported: {:?}",
@@ -481,91 +1615,144 @@ fn eval_index_expression(
     ))));
 }
 
-fn find_builtin(s: &str) -> Option<Rc<Object>> {
-    match s {
-        "len" | "first" | "last" | "rest" | "push" => Some(Rc::new(Object::Builtin(s.to_owned()))),
-        _ => None,
+fn eval_slice_expression(
+    left: Rc<Object>,
+    start: &Option<Box<Node>>,
+    end: &Option<Box<Node>>,
+    env: &mut Environment,
+) -> Option<Rc<Object>> {
+    let mut bounds = vec![];
+    for bound in [start, end].iter() {
+        match bound {
+            Some(bound) => {
+                let bound = eval(bound, env)?;
+                match bound.borrow() {
+                    Object::Integer(i) => bounds.push(Some(*i)),
+                    _ if bound.is_return_or_error() => return Some(bound),
+                    _ => {
+                        return Some(Rc::new(Object::Error(format!(
+                            "slice index must be Integer, got = {:?}",
+                            bound
+                        ))))
+                    }
+                }
+            }
+            None => bounds.push(None),
+        }
     }
-}
 
-fn do_builtin(s: &str, args: Vec<Rc<Object>>) -> Option<Rc<Object>> {
-    match s {
-        "len" => builtin_len(args),
-        "first" => builtin_first(args),
-        "last" => builtin_last(args),
-        "rest" => builtin_rest(args),
-        "push" => builtin_push(args),
-        _ => None,
+    match left.borrow() {
+        Object::StringValue(s) => {
+            let chars = s.chars().collect::<Vec<char>>();
+            let (start, end) = slice_bounds(chars.len(), bounds[0], bounds[1]);
+            Some(Rc::new(Object::StringValue(chars[start..end].iter().collect())))
+        }
+        Object::Array(elements) => {
+            let (start, end) = slice_bounds(elements.len(), bounds[0], bounds[1]);
+            Some(Rc::new(Object::Array(elements[start..end].to_vec())))
+        }
+        _ => Some(Rc::new(Object::Error(format!(
+            "slice operator not supported: {:?}",
+            left
+        )))),
     }
 }
 
-fn builtin_len(args: Vec<Rc<Object>>) -> Option<Rc<Object>> {
-    if args.len() != 1 {
-        return Some(Rc::new(Object::Error(format!("wrong number of arguments, got = {}, want = 1", args.len()))));
-    }
-
-    let o = args.get(0).unwrap();
-
-    match o.borrow() {
-        Object::StringValue(v) => Some(Rc::new(Object::Integer(v.len() as i64))),
-        Object::Array(v) => Some(Rc::new(Object::Integer(v.len() as i64))),
-        _ => Some(Rc::new(Object::Error("argument to len not supported".to_owned())))
+// A negative index counts from the end, so -1 is the last element.
+fn resolve_index(len: usize, i: i64) -> Option<usize> {
+    let i = if i < 0 { i + len as i64 } else { i };
+    if i < 0 || i >= len as i64 {
+        return None;
     }
+    return Some(i as usize);
 }
 
-fn builtin_first(args: Vec<Rc<Object>>) -> Option<Rc<Object>> {
-    if args.len() != 1 {
-        return Some(Rc::new(Object::Error(format!("wrong number of arguments, got = {}, want = 1", args.len()))));
-    }
-
-    let o = args.get(0).unwrap();
-    if let Object::Array(elements) = o.borrow() {
-        return Some(elements[0].clone());
-    } else {
-        return Some(Rc::new(Object::Error(format!("argument to first must be Array, got = {:?}", o))));
-    }
+// Bounds count from the end when negative, like indices, and are clamped past either
+// end, so a slice never fails on its length.
+fn slice_bounds(len: usize, start: Option<i64>, end: Option<i64>) -> (usize, usize) {
+    let len = len as i64;
+    let clamp = |i: i64| {
+        let i = if i < 0 { i + len } else { i };
+        i.max(0).min(len) as usize
+    };
+    let start = start.map(clamp).unwrap_or(0);
+    let end = end.map(clamp).unwrap_or(len as usize);
+    return (start, end.max(start));
 }
 
-
-fn builtin_last(args: Vec<Rc<Object>>) -> Option<Rc<Object>> {
-    if args.len() != 1 {
-        return Some(Rc::new(Object::Error(format!("wrong number of arguments, got = {}, want = 1", args.len()))));
+fn eval_member_expression(left: Rc<Object>, member: &Box<Node>) -> Option<Rc<Object>> {
+    let name = match member.borrow() {
+        Node::Identifier { token: _, value, span: _ } => value,
+        _ => {
+            return Some(Rc::new(Object::Error(format!(
+                "invalid member: {:?}",
+                member
+            ))))
+        }
+    };
+    if let Object::Hash(pairs) = left.borrow() {
+        return match hash_member(pairs, name) {
+            Some(value) => Some(value),
+            None => Some(Rc::new(object::NULL)),
+        };
     }
-
-    let o = args.get(0).unwrap();
-    if let Object::Array(elements) = o.borrow() {
-        return Some(elements[elements.len()-1].clone());
-    } else {
-        return Some(Rc::new(Object::Error(format!("argument to first must be Array, got = {:?}", o))));
+    if let Object::Struct {
+        struct_type,
+        values,
+    } = left.borrow()
+    {
+        return match struct_type.field_index(name) {
+            Some(i) => Some(values[i].clone()),
+            None => Some(Rc::new(Object::Error(format!(
+                "unknown field: {} for {}",
+                name, struct_type.name
+            )))),
+        };
     }
-}
-
-fn builtin_rest(args: Vec<Rc<Object>>) -> Option<Rc<Object>> {
-    if args.len() != 1 {
-        return Some(Rc::new(Object::Error(format!("wrong number of arguments, got = {}, want = 1", args.len()))));
+    if let Object::Variant {
+        variant_type,
+        payload,
+    } = left.borrow()
+    {
+        return match variant_type.fields.iter().position(|f| f == name) {
+            Some(i) => Some(payload[i].clone()),
+            None => Some(Rc::new(Object::Error(format!(
+                "unknown field: {} for {}",
+                name, variant_type.tag
+            )))),
+        };
     }
-
-    let o = args.get(0).unwrap();
-    if let Object::Array(elements) = o.borrow() {
-        return Some(Rc::new(Object::Array(elements[1..].to_owned())));
-    } else {
-        return Some(Rc::new(Object::Error(format!("argument to first must be Array, got = {:?}", o))));
+    if let Object::Module(module) = left.borrow() {
+        return Some(module_export(module, name));
+    }
+    if let Object::EnumType(enum_type) = left.borrow() {
+        return match enum_type.variants.iter().find(|v| v.tag == *name) {
+            Some(variant_type) => Some(variant_value(variant_type)),
+            None => Some(Rc::new(Object::Error(format!(
+                "unknown variant: {} for {}",
+                name, enum_type.name
+            )))),
+        };
     }
+    return Some(Rc::new(Object::Error(format!(
+        "member access not supported: {:?}",
+        left
+    ))));
 }
 
-fn builtin_push(args: Vec<Rc<Object>>) -> Option<Rc<Object>> {
-    if args.len() != 2 {
-        return Some(Rc::new(Object::Error(format!("wrong number of arguments, got = {}, want = 2", args.len()))));
+fn module_export(module: &Module, name: &str) -> Rc<Object> {
+    match module.exports.get(&name.to_owned()) {
+        Some(value) => value.clone(),
+        None => Rc::new(Object::Error(format!(
+            "module {} has no export '{}'",
+            module.path.display(),
+            name
+        ))),
     }
+}
 
-    let arr = args.get(0).unwrap();
-    let elm = args.get(1).unwrap();
-
-    if let Object::Array(elements) = arr.borrow() {
-        let mut elements = elements.to_owned();
-        elements.push(elm.to_owned());
-        return Some(Rc::new(Object::Array(elements)));
-    } else {
-        return Some(Rc::new(Object::Error(format!("argument to first must be Array, got = {:?}", arr))));
-    }
-}
\ No newline at end of file
+fn hash_member(pairs: &OrderedMap<HashKey, HashPair>, name: &str) -> Option<Rc<Object>> {
+    pairs
+        .get(&HashKey::String(name.to_owned()))
+        .map(|pair| pair.value.clone())
+}
@@ -0,0 +1,169 @@
+use crate::ast::Node;
+use crate::environment::{DuplicateKeyPolicy, Environment};
+use crate::evaluator::{apply_function, eval, eval_program_in_file};
+use crate::lexer::Lexer;
+use crate::native;
+use crate::object::{Object, Value};
+use crate::parser::Parser;
+use crate::token::Token;
+
+use std::error;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::rc::Rc;
+
+/// Everything that can stop a program run through an `Interpreter`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MonkeyError {
+    /// The file could not be read.
+    Io(String),
+    /// Characters the lexer does not recognise, one message per illegal token.
+    Lex(Vec<String>),
+    /// Messages reported by the parser.
+    Parse(Vec<String>),
+    /// A runtime error such as a type mismatch or an unknown identifier.
+    Runtime(String),
+    /// A value thrown with `throw` that no `try` caught.
+    Thrown(Value),
+}
+
+impl fmt::Display for MonkeyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MonkeyError::Io(message) => write!(f, "{}", message),
+            MonkeyError::Lex(errors) => write!(f, "lex error: {}", errors.join(", ")),
+            MonkeyError::Parse(errors) => write!(f, "parse error: {}", errors.join(", ")),
+            MonkeyError::Runtime(message) => write!(f, "{}", message),
+            MonkeyError::Thrown(value) => write!(f, "uncaught {}", value),
+        }
+    }
+}
+
+impl error::Error for MonkeyError {}
+
+/// Runs Monkey source against one global environment that keeps its bindings
+/// between calls, the way the REPL does.
+pub struct Interpreter {
+    env: Environment,
+}
+
+impl Interpreter {
+    pub fn new() -> Interpreter {
+        Interpreter {
+            env: Environment::new(),
+        }
+    }
+
+    /// An interpreter whose scripts can call the prelude functions.
+    pub fn new_with_prelude() -> Interpreter {
+        Interpreter {
+            env: Environment::new_with_prelude(),
+        }
+    }
+
+    /// Evaluates `source` and returns the value of its last statement.
+    pub fn eval_str(&mut self, source: &str) -> Result<Value, MonkeyError> {
+        let program = parse(source)?;
+        to_result(eval(&program, &mut self.env))
+    }
+
+    /// Evaluates the file at `path`; `import` paths inside it resolve relative to its directory.
+    pub fn eval_file(&mut self, path: &Path) -> Result<Value, MonkeyError> {
+        let read_error = |e| MonkeyError::Io(format!("cannot read {}: {}", path.display(), e));
+        let path = fs::canonicalize(path).map_err(read_error)?;
+        let source = fs::read_to_string(&path).map_err(read_error)?;
+        let program = parse(&source)?;
+        to_result(eval_program_in_file(&program, path, &mut self.env))
+    }
+
+    /// Calls the global function, builtin or constructor `name` with `args`.
+    pub fn call_function(&mut self, name: &str, args: &[Value]) -> Result<Value, MonkeyError> {
+        let function = self
+            .env
+            .get(name)
+            .or_else(|| self.env.get_builtin(name))
+            .ok_or_else(|| MonkeyError::Runtime(format!("identifier not found: {:?}", name)))?;
+        match *function {
+            Object::Function { .. }
+            | Object::Builtin(_)
+            | Object::StructType(_)
+            | Object::VariantType(_) => to_result(apply_function(function.clone(), args.to_vec())),
+            _ => Err(MonkeyError::Runtime(format!(
+                "not a function: {}",
+                function.type_name()
+            ))),
+        }
+    }
+
+    pub fn get_global(&self, name: &str) -> Option<Value> {
+        self.env.get(name)
+    }
+
+    pub fn set_global(&mut self, name: &str, value: Value) {
+        self.env.set(name.to_owned(), value);
+    }
+
+    /// See `Environment::register_fn`.
+    pub fn register_fn<F>(&mut self, name: &str, f: F)
+    where
+        F: Fn(&[Value]) -> Result<Value, native::Error> + 'static,
+    {
+        self.env.register_fn(name, f);
+    }
+
+    /// See `Environment::remove_fn`.
+    pub fn remove_fn(&mut self, name: &str) {
+        self.env.remove_fn(name);
+    }
+
+    /// See `Environment::set_duplicate_key_policy`.
+    pub fn set_duplicate_key_policy(&mut self, policy: DuplicateKeyPolicy) {
+        self.env.set_duplicate_key_policy(policy);
+    }
+
+    pub fn environment(&mut self) -> &mut Environment {
+        &mut self.env
+    }
+}
+
+impl Default for Interpreter {
+    fn default() -> Interpreter {
+        Interpreter::new()
+    }
+}
+
+fn parse(source: &str) -> Result<Box<Node>, MonkeyError> {
+    let mut lexer = Lexer::new(source.to_owned());
+    let mut illegal = vec![];
+    loop {
+        match lexer.token() {
+            Token::Eof => break,
+            Token::Illegal(s) => illegal.push(format!("illegal token: {:?}", s)),
+            _ => {}
+        }
+    }
+    if !illegal.is_empty() {
+        return Err(MonkeyError::Lex(illegal));
+    }
+
+    let mut parser = Parser::new(Lexer::new(source.to_owned()));
+    let program = parser.parse_program();
+    match program {
+        Some(program) if parser.errors().is_empty() => Ok(program),
+        _ => Err(MonkeyError::Parse(parser.errors().clone())),
+    }
+}
+
+fn to_result(result: Option<Rc<Object>>) -> Result<Value, MonkeyError> {
+    let result = match result {
+        Some(result) => result,
+        None => return Err(MonkeyError::Runtime("internal: evaluation produced no value".to_owned())),
+    };
+    match &*result {
+        Object::Error(message) => Err(MonkeyError::Runtime(message.to_owned())),
+        Object::Thrown(value) => Err(MonkeyError::Thrown(value.clone())),
+        Object::ReturnValue(value) => Ok(value.clone()),
+        _ => Ok(result),
+    }
+}
@@ -0,0 +1,412 @@
+use crate::object::{HashKey, HashPair, Object, Value};
+use crate::ordered_map::OrderedMap;
+
+use std::rc::Rc;
+
+/// A JSON document. Numbers are integers only, since those are the only numbers Monkey has,
+/// and object members keep their order.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(i64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    /// Parses a complete JSON document; the error names the line and column where it failed.
+    pub fn parse(input: &str) -> Result<Json, String> {
+        let mut reader = Reader {
+            chars: input.chars().collect(),
+            position: 0,
+            depth: 0,
+        };
+        reader.skip_whitespace();
+        let json = reader.read_value().and_then(|json| {
+            reader.skip_whitespace();
+            match reader.peek() {
+                None => Ok(json),
+                Some(_) => Err("unexpected trailing characters".to_owned()),
+            }
+        });
+        json.map_err(|e| {
+            let (line, column) = reader.line_and_column();
+            format!("invalid JSON: {} at line {}, column {}", e, line, column)
+        })
+    }
+
+    /// Writes the document on one line, or one member per line indented by `indent` spaces.
+    pub fn stringify(&self, indent: Option<usize>) -> String {
+        let mut out = String::new();
+        write_json(self, indent, 0, &mut out);
+        out
+    }
+
+    /// The member `name` of an object.
+    pub fn get(&self, name: &str) -> Option<&Json> {
+        match self {
+            Json::Object(members) => members.iter().find(|(k, _)| k == name).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+}
+
+// How deeply arrays and objects may nest. They are read recursively, so deeper input
+// is rejected instead of overflowing the stack.
+const MAX_DEPTH: usize = 512;
+
+struct Reader {
+    chars: Vec<char>,
+    position: usize,
+    // The number of arrays and objects being read.
+    depth: usize,
+}
+
+impl Reader {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.position).cloned()
+    }
+
+    fn next(&mut self) -> Result<char, String> {
+        let c = self.peek().ok_or_else(|| "unexpected end of input".to_owned())?;
+        self.position += 1;
+        Ok(c)
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), String> {
+        match self.next()? {
+            c if c == expected => Ok(()),
+            c => {
+                self.position -= 1;
+                Err(format!("expected {:?}, got {:?}", expected, c))
+            }
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(' ') | Some('\t') | Some('\n') | Some('\r') = self.peek() {
+            self.position += 1;
+        }
+    }
+
+    fn line_and_column(&self) -> (usize, usize) {
+        let before = &self.chars[..self.position.min(self.chars.len())];
+        let line = before.iter().filter(|c| **c == '\n').count() + 1;
+        let column = before.iter().rev().take_while(|c| **c != '\n').count() + 1;
+        (line, column)
+    }
+
+    fn read_value(&mut self) -> Result<Json, String> {
+        match self.peek() {
+            Some('n') => self.read_keyword("null", Json::Null),
+            Some('t') => self.read_keyword("true", Json::Bool(true)),
+            Some('f') => self.read_keyword("false", Json::Bool(false)),
+            Some('"') => self.read_string().map(Json::String),
+            Some('[') => self.read_nested(Reader::read_array),
+            Some('{') => self.read_nested(Reader::read_object),
+            Some(c) if c == '-' || c.is_ascii_digit() => self.read_number(),
+            Some(c) => Err(format!("unexpected character {:?}", c)),
+            None => Err("unexpected end of input".to_owned()),
+        }
+    }
+
+    fn read_nested(&mut self, read: fn(&mut Reader) -> Result<Json, String>) -> Result<Json, String> {
+        if self.depth == MAX_DEPTH {
+            return Err("nesting too deep".to_owned());
+        }
+        self.depth += 1;
+        let json = read(self);
+        self.depth -= 1;
+        json
+    }
+
+    fn read_keyword(&mut self, keyword: &str, value: Json) -> Result<Json, String> {
+        let start = self.position;
+        for expected in keyword.chars() {
+            if self.peek() != Some(expected) {
+                self.position = start;
+                return Err(format!("expected {}", keyword));
+            }
+            self.position += 1;
+        }
+        Ok(value)
+    }
+
+    fn read_number(&mut self) -> Result<Json, String> {
+        let start = self.position;
+        if self.peek() == Some('-') {
+            self.position += 1;
+        }
+        let digits = self.position;
+        while let Some('0'..='9') = self.peek() {
+            self.position += 1;
+        }
+        let text = self.chars[start..self.position].iter().collect::<String>();
+        if self.position == digits || (self.chars[digits] == '0' && self.position > digits + 1) {
+            self.position = start;
+            return Err(format!("invalid number {:?}", text));
+        }
+        if let Some('.') | Some('e') | Some('E') = self.peek() {
+            self.position = start;
+            return Err("only integer numbers are supported".to_owned());
+        }
+        text.parse::<i64>().map(Json::Number).map_err(|_| {
+            self.position = start;
+            format!("number out of range: {}", text)
+        })
+    }
+
+    fn read_string(&mut self) -> Result<String, String> {
+        self.expect('"')?;
+        let mut s = String::new();
+        loop {
+            match self.next()? {
+                '"' => return Ok(s),
+                '\\' => s.push(self.read_escape()?),
+                c if (c as u32) < 0x20 => {
+                    self.position -= 1;
+                    return Err("control character in string".to_owned());
+                }
+                c => s.push(c),
+            }
+        }
+    }
+
+    fn read_escape(&mut self) -> Result<char, String> {
+        let c = match self.next()? {
+            '"' => '"',
+            '\\' => '\\',
+            '/' => '/',
+            'b' => '\u{8}',
+            'f' => '\u{c}',
+            'n' => '\n',
+            'r' => '\r',
+            't' => '\t',
+            'u' => {
+                let high = self.read_hex()?;
+                if (0xD800..0xDC00).contains(&high) {
+                    self.expect('\\')?;
+                    self.expect('u')?;
+                    let low = self.read_hex()?;
+                    if !(0xDC00..0xE000).contains(&low) {
+                        return Err("invalid surrogate pair".to_owned());
+                    }
+                    let code = 0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00);
+                    return std::char::from_u32(code).ok_or_else(|| "invalid escape".to_owned());
+                }
+                return std::char::from_u32(high).ok_or_else(|| "invalid escape".to_owned());
+            }
+            c => {
+                self.position -= 1;
+                return Err(format!("invalid escape {:?}", c));
+            }
+        };
+        Ok(c)
+    }
+
+    fn read_hex(&mut self) -> Result<u32, String> {
+        let mut code = 0;
+        for _ in 0..4 {
+            let digit = self.next()?.to_digit(16).ok_or_else(|| {
+                self.position -= 1;
+                "invalid \\u escape".to_owned()
+            })?;
+            code = code * 16 + digit;
+        }
+        Ok(code)
+    }
+
+    fn read_array(&mut self) -> Result<Json, String> {
+        self.expect('[')?;
+        self.skip_whitespace();
+        let mut elements = vec![];
+        if self.peek() == Some(']') {
+            self.position += 1;
+            return Ok(Json::Array(elements));
+        }
+        loop {
+            self.skip_whitespace();
+            elements.push(self.read_value()?);
+            self.skip_whitespace();
+            match self.next()? {
+                ',' => continue,
+                ']' => return Ok(Json::Array(elements)),
+                c => {
+                    self.position -= 1;
+                    return Err(format!("expected ',' or ']', got {:?}", c));
+                }
+            }
+        }
+    }
+
+    fn read_object(&mut self) -> Result<Json, String> {
+        self.expect('{')?;
+        self.skip_whitespace();
+        let mut members = vec![];
+        if self.peek() == Some('}') {
+            self.position += 1;
+            return Ok(Json::Object(members));
+        }
+        loop {
+            self.skip_whitespace();
+            let key = self.read_string()?;
+            self.skip_whitespace();
+            self.expect(':')?;
+            self.skip_whitespace();
+            members.push((key, self.read_value()?));
+            self.skip_whitespace();
+            match self.next()? {
+                ',' => continue,
+                '}' => return Ok(Json::Object(members)),
+                c => {
+                    self.position -= 1;
+                    return Err(format!("expected ',' or '}}', got {:?}", c));
+                }
+            }
+        }
+    }
+}
+
+fn write_json(json: &Json, indent: Option<usize>, depth: usize, out: &mut String) {
+    match json {
+        Json::Null => out.push_str("null"),
+        Json::Bool(b) => out.push_str(&b.to_string()),
+        Json::Number(n) => out.push_str(&n.to_string()),
+        Json::String(s) => write_string(s, out),
+        Json::Array(elements) => {
+            write_items(elements.iter(), '[', ']', indent, depth, out, |e, out| {
+                write_json(e, indent, depth + 1, out)
+            })
+        }
+        Json::Object(members) => {
+            write_items(members.iter(), '{', '}', indent, depth, out, |(k, v), out| {
+                write_string(k, out);
+                out.push_str(if indent.is_some() { ": " } else { ":" });
+                write_json(v, indent, depth + 1, out);
+            })
+        }
+    }
+}
+
+fn write_items<I, F>(
+    items: I,
+    open: char,
+    close: char,
+    indent: Option<usize>,
+    depth: usize,
+    out: &mut String,
+    write_item: F,
+) where
+    I: ExactSizeIterator,
+    F: Fn(I::Item, &mut String),
+{
+    out.push(open);
+    let empty = items.len() == 0;
+    for (i, item) in items.enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        if let Some(width) = indent {
+            out.push('\n');
+            out.push_str(&" ".repeat(width * (depth + 1)));
+        }
+        write_item(item, out);
+    }
+    if let (Some(width), false) = (indent, empty) {
+        out.push('\n');
+        out.push_str(&" ".repeat(width * depth));
+    }
+    out.push(close);
+}
+
+fn write_string(s: &str, out: &mut String) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            '\u{8}' => out.push_str("\\b"),
+            '\u{c}' => out.push_str("\\f"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+/// The Monkey value for a JSON document: objects become hashes with string keys.
+pub fn to_value(json: &Json) -> Value {
+    Rc::new(match json {
+        Json::Null => Object::Null,
+        Json::Bool(b) => Object::Bool(*b),
+        Json::Number(n) => Object::Integer(*n),
+        Json::String(s) => Object::StringValue(s.to_owned()),
+        Json::Array(elements) => Object::Array(elements.iter().map(to_value).collect()),
+        Json::Object(members) => {
+            let mut pairs = OrderedMap::new();
+            for (key, value) in members {
+                pairs.insert(
+                    HashKey::String(key.to_owned()),
+                    HashPair {
+                        key: Rc::new(Object::StringValue(key.to_owned())),
+                        value: to_value(value),
+                    },
+                );
+            }
+            Object::Hash(pairs)
+        }
+    })
+}
+
+/// The JSON document for a Monkey value. Sets become arrays and structs become objects;
+/// hashes need string keys, and functions and other values have no JSON form.
+pub fn from_value(value: &Object) -> Result<Json, String> {
+    match value {
+        Object::Null => Ok(Json::Null),
+        Object::Bool(b) => Ok(Json::Bool(*b)),
+        Object::Integer(n) => Ok(Json::Number(*n)),
+        Object::StringValue(s) => Ok(Json::String(s.to_owned())),
+        Object::Array(elements) => elements
+            .iter()
+            .map(|e| from_value(e))
+            .collect::<Result<Vec<Json>, String>>()
+            .map(Json::Array),
+        Object::Set(elements) => elements
+            .values()
+            .map(|e| from_value(e))
+            .collect::<Result<Vec<Json>, String>>()
+            .map(Json::Array),
+        Object::Hash(pairs) => {
+            let mut members = vec![];
+            for pair in pairs.values() {
+                let key = match pair.key.as_ref() {
+                    Object::StringValue(key) => key.to_owned(),
+                    key => {
+                        return Err(format!(
+                            "JSON object keys must be String, got = {}",
+                            key.type_name()
+                        ))
+                    }
+                };
+                members.push((key, from_value(&pair.value)?));
+            }
+            Ok(Json::Object(members))
+        }
+        Object::Struct {
+            struct_type,
+            values,
+        } => {
+            let mut members = vec![];
+            for (field, value) in struct_type.fields.iter().zip(values.iter()) {
+                members.push((field.to_owned(), from_value(value)?));
+            }
+            Ok(Json::Object(members))
+        }
+        o => Err(format!("cannot convert {} to JSON", o.type_name())),
+    }
+}
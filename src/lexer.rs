@@ -1,5 +1,5 @@
-use crate::token::Token;
 use crate::token::Token::*;
+use crate::token::{Position, Span, Token};
 
 use std::iter::FromIterator;
 use std::str::FromStr;
@@ -8,23 +8,38 @@ pub struct Lexer {
     input: Vec<char>,
     position: usize,
     char: Option<char>,
+    // Offset of the first character of the last token read.
+    start: usize,
+    // Offset of the first character of each line.
+    line_starts: Vec<usize>,
 }
 
 impl Lexer {
     pub fn new(input: String) -> Lexer {
         let chars = input.chars().collect::<Vec<char>>();
         let first = chars.get(0).and_then(|c| Some(*c));
+        let mut line_starts = vec![0];
+        line_starts.extend(
+            chars
+                .iter()
+                .enumerate()
+                .filter(|(_, c)| **c == '\n')
+                .map(|(i, _)| i + 1),
+        );
 
         let lexer = Lexer {
             input: chars,
             position: 0,
             char: first,
+            start: 0,
+            line_starts,
         };
         return lexer;
     }
 
     pub fn token(&mut self) -> Token {
         self.skip_whitespace();
+        self.start = self.position;
         if self.char.is_none() {
             return Eof;
         }
@@ -45,6 +60,10 @@ impl Lexer {
                 self.next();
                 self.next();
                 Eq
+            } else if self.peek_char().filter(|cc| *cc == '>').is_some() {
+                self.next();
+                self.next();
+                FatArrow
             } else {
                 self.next();
                 Assign
@@ -58,6 +77,22 @@ impl Lexer {
                 self.next();
                 Bang
             }
+        } else if c == '|' && self.peek_char().filter(|cc| *cc == '>').is_some() {
+            self.next();
+            self.next();
+            Pipe
+        } else if c == '?' && self.peek_char().filter(|cc| *cc == '?').is_some() {
+            self.next();
+            self.next();
+            NullCoalesce
+        } else if c == '?' && self.peek_char().filter(|cc| *cc == '.').is_some() {
+            self.next();
+            self.next();
+            OptionalDot
+        } else if c == '#' && self.peek_char().filter(|cc| *cc == '{').is_some() {
+            self.next();
+            self.next();
+            SetBrace
         } else if c == '"' {
             let str = self.read_string();
             Str(str)
@@ -72,6 +107,44 @@ impl Lexer {
         return token;
     }
 
+    /// Reads tokens into `f` until it returns a result, then rewinds to where it started,
+    /// for the parser to look further ahead than its one peek token. `f` must stop at `Eof`.
+    pub fn scan_ahead<T, F>(&mut self, mut f: F) -> T
+    where
+        F: FnMut(Token) -> Option<T>,
+    {
+        let (position, char, start) = (self.position, self.char, self.start);
+        let result = loop {
+            if let Some(result) = f(self.token()) {
+                break result;
+            }
+        };
+        self.position = position;
+        self.char = char;
+        self.start = start;
+        result
+    }
+
+    /// Where the token last returned by `token` is in the source.
+    pub fn span(&self) -> Span {
+        Span {
+            start: self.position_at(self.start),
+            end: self.position_at(self.position.min(self.input.len())),
+        }
+    }
+
+    fn position_at(&self, offset: usize) -> Position {
+        let line = match self.line_starts.binary_search(&offset) {
+            Ok(line) => line,
+            Err(line) => line - 1,
+        };
+        Position {
+            line: line + 1,
+            column: offset - self.line_starts[line] + 1,
+            offset,
+        }
+    }
+
     pub fn next(&mut self) {
         self.position += 1;
         self.char = self.input.get(self.position).and_then(|c| Some(*c));
@@ -216,6 +289,160 @@ let result = add(five, ten);
     assert_eq!(lexer.token(), Eof);
 }
 
+#[test]
+fn test_next_dot() {
+    let input = r#"arr.push(4).len()"#.to_string();
+    let mut lexer = Lexer::new(input);
+
+    assert_eq!(lexer.token(), Ident("arr".to_string()));
+    assert_eq!(lexer.token(), Dot);
+    assert_eq!(lexer.token(), Ident("push".to_string()));
+    assert_eq!(lexer.token(), LParen);
+    assert_eq!(lexer.token(), Int("4".to_string()));
+    assert_eq!(lexer.token(), RParen);
+    assert_eq!(lexer.token(), Dot);
+    assert_eq!(lexer.token(), Ident("len".to_string()));
+    assert_eq!(lexer.token(), LParen);
+    assert_eq!(lexer.token(), RParen);
+    assert_eq!(lexer.token(), Eof);
+}
+
+#[test]
+fn test_next_pipe() {
+    let input = r#"xs |> map(f) | 1"#.to_string();
+    let mut lexer = Lexer::new(input);
+
+    assert_eq!(lexer.token(), Ident("xs".to_string()));
+    assert_eq!(lexer.token(), Pipe);
+    assert_eq!(lexer.token(), Ident("map".to_string()));
+    assert_eq!(lexer.token(), LParen);
+    assert_eq!(lexer.token(), Ident("f".to_string()));
+    assert_eq!(lexer.token(), RParen);
+    assert_eq!(lexer.token(), Illegal("|".to_string()));
+    assert_eq!(lexer.token(), Int("1".to_string()));
+    assert_eq!(lexer.token(), Eof);
+}
+
+#[test]
+fn test_next_null_operators() {
+    let input = r#"a ?? null; a?.b?.[0]"#.to_string();
+    let mut lexer = Lexer::new(input);
+
+    assert_eq!(lexer.token(), Ident("a".to_string()));
+    assert_eq!(lexer.token(), NullCoalesce);
+    assert_eq!(lexer.token(), Null);
+    assert_eq!(lexer.token(), SemiColon);
+    assert_eq!(lexer.token(), Ident("a".to_string()));
+    assert_eq!(lexer.token(), OptionalDot);
+    assert_eq!(lexer.token(), Ident("b".to_string()));
+    assert_eq!(lexer.token(), OptionalDot);
+    assert_eq!(lexer.token(), LBracket);
+    assert_eq!(lexer.token(), Int("0".to_string()));
+    assert_eq!(lexer.token(), RBracket);
+    assert_eq!(lexer.token(), Eof);
+}
+
+#[test]
+fn test_next_try() {
+    let input = r#"try { throw "x"; } catch (e) { e } finally { 1 }"#.to_string();
+    let mut lexer = Lexer::new(input);
+
+    assert_eq!(lexer.token(), Try);
+    assert_eq!(lexer.token(), LBrace);
+    assert_eq!(lexer.token(), Throw);
+    assert_eq!(lexer.token(), Str("x".to_string()));
+    assert_eq!(lexer.token(), SemiColon);
+    assert_eq!(lexer.token(), RBrace);
+    assert_eq!(lexer.token(), Catch);
+    assert_eq!(lexer.token(), LParen);
+    assert_eq!(lexer.token(), Ident("e".to_string()));
+    assert_eq!(lexer.token(), RParen);
+    assert_eq!(lexer.token(), LBrace);
+    assert_eq!(lexer.token(), Ident("e".to_string()));
+    assert_eq!(lexer.token(), RBrace);
+    assert_eq!(lexer.token(), Finally);
+    assert_eq!(lexer.token(), LBrace);
+    assert_eq!(lexer.token(), Int("1".to_string()));
+    assert_eq!(lexer.token(), RBrace);
+    assert_eq!(lexer.token(), Eof);
+}
+
+#[test]
+fn test_next_question() {
+    let input = r#"let v = parse(s)?;"#.to_string();
+    let mut lexer = Lexer::new(input);
+
+    assert_eq!(lexer.token(), Let);
+    assert_eq!(lexer.token(), Ident("v".to_string()));
+    assert_eq!(lexer.token(), Assign);
+    assert_eq!(lexer.token(), Ident("parse".to_string()));
+    assert_eq!(lexer.token(), LParen);
+    assert_eq!(lexer.token(), Ident("s".to_string()));
+    assert_eq!(lexer.token(), RParen);
+    assert_eq!(lexer.token(), Question);
+    assert_eq!(lexer.token(), SemiColon);
+    assert_eq!(lexer.token(), Eof);
+}
+
+#[test]
+fn test_next_set() {
+    let input = r#"#{1, 2} #"#.to_string();
+    let mut lexer = Lexer::new(input);
+
+    assert_eq!(lexer.token(), SetBrace);
+    assert_eq!(lexer.token(), Int("1".to_string()));
+    assert_eq!(lexer.token(), Comma);
+    assert_eq!(lexer.token(), Int("2".to_string()));
+    assert_eq!(lexer.token(), RBrace);
+    assert_eq!(lexer.token(), Illegal("#".to_string()));
+    assert_eq!(lexer.token(), Eof);
+}
+
+#[test]
+fn test_next_import() {
+    let input = r#"import "math.monkey" as math; export let x = 1;"#.to_string();
+    let mut lexer = Lexer::new(input);
+
+    assert_eq!(lexer.token(), Import);
+    assert_eq!(lexer.token(), Str("math.monkey".to_string()));
+    assert_eq!(lexer.token(), As);
+    assert_eq!(lexer.token(), Ident("math".to_string()));
+    assert_eq!(lexer.token(), SemiColon);
+    assert_eq!(lexer.token(), Export);
+    assert_eq!(lexer.token(), Let);
+    assert_eq!(lexer.token(), Ident("x".to_string()));
+    assert_eq!(lexer.token(), Assign);
+    assert_eq!(lexer.token(), Int("1".to_string()));
+    assert_eq!(lexer.token(), SemiColon);
+    assert_eq!(lexer.token(), Eof);
+}
+
+#[test]
+fn test_next_match() {
+    let input = r#"enum Shape { Empty } match (s) { Empty => 0, _ => 1 }"#.to_string();
+    let mut lexer = Lexer::new(input);
+
+    assert_eq!(lexer.token(), Enum);
+    assert_eq!(lexer.token(), Ident("Shape".to_string()));
+    assert_eq!(lexer.token(), LBrace);
+    assert_eq!(lexer.token(), Ident("Empty".to_string()));
+    assert_eq!(lexer.token(), RBrace);
+    assert_eq!(lexer.token(), Match);
+    assert_eq!(lexer.token(), LParen);
+    assert_eq!(lexer.token(), Ident("s".to_string()));
+    assert_eq!(lexer.token(), RParen);
+    assert_eq!(lexer.token(), LBrace);
+    assert_eq!(lexer.token(), Ident("Empty".to_string()));
+    assert_eq!(lexer.token(), FatArrow);
+    assert_eq!(lexer.token(), Int("0".to_string()));
+    assert_eq!(lexer.token(), Comma);
+    assert_eq!(lexer.token(), Ident("_".to_string()));
+    assert_eq!(lexer.token(), FatArrow);
+    assert_eq!(lexer.token(), Int("1".to_string()));
+    assert_eq!(lexer.token(), RBrace);
+    assert_eq!(lexer.token(), Eof);
+}
+
 #[test]
 fn test_read_identifier() {
     let input = r#"let five = 5;"#.to_string();
@@ -239,4 +466,29 @@ fn test_read_string() {
 
     assert_eq!(lexer.token(), SemiColon);
     assert_eq!(lexer.token(), Int("10".to_string()));
-}
\ No newline at end of file
+}
+#[test]
+fn test_span() {
+    use crate::token::{Position, Span};
+
+    let input = "let x = \"hi\";\n  x ==".to_string();
+    let mut lexer = Lexer::new(input);
+    let mut spans = vec![];
+    loop {
+        let token = lexer.token();
+        spans.push(lexer.span());
+        if token == Eof {
+            break;
+        }
+    }
+    let at = |line, column, offset| Position {
+        line,
+        column,
+        offset,
+    };
+    assert_eq!(spans[0], Span { start: at(1, 1, 0), end: at(1, 4, 3) });
+    assert_eq!(spans[3], Span { start: at(1, 9, 8), end: at(1, 13, 12) });
+    assert_eq!(spans[5], Span { start: at(2, 3, 16), end: at(2, 4, 17) });
+    assert_eq!(spans[6], Span { start: at(2, 5, 18), end: at(2, 7, 20) });
+    assert_eq!(spans[7], Span { start: at(2, 7, 20), end: at(2, 7, 20) });
+}
@@ -7,10 +7,20 @@ mod tests {
 }
 
 pub mod ast;
+pub mod ast_json;
+pub mod builtins;
+pub mod convert;
 pub mod environment;
 pub mod evaluator;
+pub mod interpreter;
+pub mod json;
 pub mod lexer;
+pub mod native;
 pub mod object;
+pub mod ordered_map;
 pub mod parser;
+pub mod prelude;
 pub mod repl;
+#[cfg(feature = "serde")]
+pub mod serde_value;
 pub mod token;
@@ -1,7 +1,62 @@
 extern crate rust_monkey;
 
+use rust_monkey::ast_json;
+use rust_monkey::interpreter::Interpreter;
+use rust_monkey::lexer::Lexer;
+use rust_monkey::parser::Parser;
 use rust_monkey::repl::start;
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::process;
 
 fn main() {
-    start();
+    let args = env::args().skip(1).collect::<Vec<String>>();
+    let no_prelude = args.iter().any(|arg| arg == "--no-prelude");
+    let dump_ast = args.iter().find_map(|arg| arg.strip_prefix("--dump-ast="));
+    let path = match args.iter().find(|arg| !arg.starts_with("--")) {
+        Some(path) => path,
+        None if dump_ast.is_some() => fail("--dump-ast needs a file to parse"),
+        None => return start(),
+    };
+
+    if let Some(format) = dump_ast {
+        return dump(path, format);
+    }
+
+    let mut interpreter = if no_prelude {
+        Interpreter::new()
+    } else {
+        Interpreter::new_with_prelude()
+    };
+    if let Err(e) = interpreter.eval_file(Path::new(path)) {
+        fail(&e.to_string());
+    }
+}
+
+fn dump(path: &str, format: &str) {
+    if format != "json" {
+        fail(&format!("unsupported --dump-ast format: {}", format));
+    }
+    let source = match fs::read_to_string(path) {
+        Ok(source) => source,
+        Err(e) => fail(&format!("{}: {}", path, e)),
+    };
+    let mut parser = Parser::new(Lexer::new(source));
+    let program = parser.parse_program();
+    if !parser.errors().is_empty() {
+        fail(&parser.errors().join("\n"));
+    }
+    match program {
+        Some(program) => {
+            let json = ast_json::to_json(&program, true);
+            println!("{}", json.stringify(Some(2)));
+        }
+        None => fail("failed to parse program"),
+    }
+}
+
+fn fail(message: &str) -> ! {
+    eprintln!("ERROR: {}", message);
+    process::exit(1);
 }
@@ -0,0 +1,124 @@
+use crate::object::{HashKey, HashPair, Object, Value};
+use crate::ordered_map::OrderedMap;
+
+use std::fmt;
+
+/// The error a host function returns; scripts see it as a runtime error with this message.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Error {
+    message: String,
+    // For an argument of the wrong type, what was expected and what was passed, so
+    // scripts get the builtins' wording once the function's name is known.
+    mismatch: Option<(&'static str, Value)>,
+}
+
+impl Error {
+    pub fn new<S: Into<String>>(message: S) -> Error {
+        Error {
+            message: message.into(),
+            mismatch: None,
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    // The message a script sees when the host function `name` fails.
+    pub(crate) fn message_in(&self, name: &str) -> String {
+        match &self.mismatch {
+            Some((want, got)) => format!("argument to {} must be {}, got = {:?}", name, want, got),
+            None => self.message.clone(),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<String> for Error {
+    fn from(message: String) -> Error {
+        Error::new(message)
+    }
+}
+
+impl From<&str> for Error {
+    fn from(message: &str) -> Error {
+        Error::new(message)
+    }
+}
+
+/// Fails unless exactly `want` arguments were passed.
+pub fn expect_arity(args: &[Value], want: usize) -> Result<(), Error> {
+    if args.len() != want {
+        return Err(Error::new(format!(
+            "wrong number of arguments, got = {}, want = {}",
+            args.len(),
+            want
+        )));
+    }
+    Ok(())
+}
+
+/// The argument at `i`, or an error if fewer were passed.
+pub fn arg(args: &[Value], i: usize) -> Result<&Value, Error> {
+    args.get(i).ok_or_else(|| {
+        Error::new(format!(
+            "wrong number of arguments, got = {}, want = {}",
+            args.len(),
+            i + 1
+        ))
+    })
+}
+
+fn type_error(i: usize, want: &'static str, got: &Value) -> Error {
+    Error {
+        message: format!("argument {} must be {}, got = {}", i + 1, want, got.type_name()),
+        mismatch: Some((want, got.clone())),
+    }
+}
+
+pub fn int_arg(args: &[Value], i: usize) -> Result<i64, Error> {
+    let arg = arg(args, i)?;
+    match arg.as_ref() {
+        Object::Integer(v) => Ok(*v),
+        _ => Err(type_error(i, "Integer", arg)),
+    }
+}
+
+pub fn bool_arg(args: &[Value], i: usize) -> Result<bool, Error> {
+    let arg = arg(args, i)?;
+    match arg.as_ref() {
+        Object::Bool(v) => Ok(*v),
+        _ => Err(type_error(i, "Bool", arg)),
+    }
+}
+
+pub fn str_arg(args: &[Value], i: usize) -> Result<&str, Error> {
+    let arg = arg(args, i)?;
+    match arg.as_ref() {
+        Object::StringValue(v) => Ok(v),
+        _ => Err(type_error(i, "String", arg)),
+    }
+}
+
+pub fn array_arg(args: &[Value], i: usize) -> Result<&[Value], Error> {
+    let arg = arg(args, i)?;
+    match arg.as_ref() {
+        Object::Array(v) => Ok(v),
+        _ => Err(type_error(i, "Array", arg)),
+    }
+}
+
+pub fn hash_arg(args: &[Value], i: usize) -> Result<&OrderedMap<HashKey, HashPair>, Error> {
+    let arg = arg(args, i)?;
+    match arg.as_ref() {
+        Object::Hash(v) => Ok(v),
+        _ => Err(type_error(i, "Hash", arg)),
+    }
+}
@@ -1,8 +1,13 @@
 use crate::ast::Node;
 use crate::environment::Environment;
+use crate::native;
+use crate::ordered_map::OrderedMap;
 
+use std::cell::RefCell;
 use std::rc::Rc;
 use std::collections::HashMap;
+use std::fmt;
+use std::path::PathBuf;
 
 #[derive(Debug, Clone)]
 pub enum Object {
@@ -12,19 +17,109 @@ pub enum Object {
     StringValue(String),
     ReturnValue(Rc<Object>),
     Error(String),
+    Thrown(Rc<Object>),
+    Ok(Rc<Object>),
+    Err(Rc<Object>),
     Function {
+        name: Option<String>,
         parameters: Vec<Box<Node>>,
         body: Box<Node>,
         environment: Environment,
     },
-    Builtin(String),
+    Builtin(Rc<Builtin>),
     Array(Vec<Rc<Object>>),
-    Hash(HashMap<String, HashPair>)
+    Hash(OrderedMap<HashKey, HashPair>),
+    Set(OrderedMap<HashKey, Rc<Object>>),
+    StructType(Rc<StructType>),
+    Struct {
+        struct_type: Rc<StructType>,
+        values: Vec<Rc<Object>>,
+    },
+    EnumType(Rc<EnumType>),
+    VariantType(Rc<VariantType>),
+    Variant {
+        variant_type: Rc<VariantType>,
+        payload: Vec<Rc<Object>>,
+    },
+    Module(Rc<Module>),
+}
+
+/// The values scripts and host code exchange.
+pub type Value = Rc<Object>;
+
+/// A host function callable from scripts.
+pub type NativeFn = dyn Fn(&[Value]) -> Result<Value, native::Error>;
+
+// One of the default builtins, looked up by name, or a closure registered by the host.
+pub struct Builtin {
+    pub name: String,
+    pub native: Option<Rc<NativeFn>>,
+}
+
+impl fmt::Debug for Builtin {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Builtin")
+            .field("name", &self.name)
+            .field("native", &self.native.is_some())
+            .finish()
+    }
+}
+
+// A `struct` declaration; calling it constructs a Struct with one value per field, in order.
+// Methods are added by `impl` blocks and take the instance as their first parameter.
+#[derive(Debug)]
+pub struct StructType {
+    pub name: String,
+    pub fields: Vec<String>,
+    pub methods: RefCell<HashMap<String, Rc<Object>>>,
+}
+
+// An `enum` declaration; each variant with fields is a constructor for a tagged Variant.
+#[derive(Debug)]
+pub struct EnumType {
+    pub name: String,
+    pub variants: Vec<Rc<VariantType>>,
+}
+
+#[derive(Debug)]
+pub struct VariantType {
+    pub enum_name: String,
+    pub tag: String,
+    pub fields: Vec<String>,
+}
+
+// The namespace an `import` binds: the names the module at `path` exported, in order.
+#[derive(Debug)]
+pub struct Module {
+    pub path: PathBuf,
+    pub exports: OrderedMap<String, Rc<Object>>,
+}
+
+impl StructType {
+    pub fn field_index(&self, name: &str) -> Option<usize> {
+        self.fields.iter().position(|f| f == name)
+    }
 }
 
+// The hashable form of a value used as a hash key. Two keys are equal exactly when
+// the values they were made from are equal.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum HashKey {
+    Integer(i64),
+    Bool(bool),
+    String(String),
+    Array(Vec<HashKey>),
+    Variant {
+        enum_name: String,
+        tag: String,
+        payload: Vec<HashKey>,
+    },
+}
+
+// A hash entry keeps the original key so keys can be iterated and printed.
 #[derive(Debug, Clone)]
 pub struct HashPair {
-    pub key: String,
+    pub key: Rc<Object>,
     pub value: Rc<Object>
 }
 
@@ -45,15 +140,83 @@ impl Object {
         }
     }
 
+    // The type name used in error messages.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Object::Null => "Null",
+            Object::Integer(_) => "Integer",
+            Object::Bool(_) => "Bool",
+            Object::StringValue(_) => "String",
+            Object::ReturnValue(v) => v.type_name(),
+            Object::Error(_) | Object::Thrown(_) => "Error",
+            Object::Ok(_) | Object::Err(_) => "Result",
+            Object::Function { .. } => "Function",
+            Object::Builtin(_) => "Builtin",
+            Object::Array(_) => "Array",
+            Object::Hash(_) => "Hash",
+            Object::Set(_) => "Set",
+            Object::StructType(_) => "StructType",
+            Object::Struct { .. } => "Struct",
+            Object::EnumType(_) => "EnumType",
+            Object::VariantType(_) => "VariantType",
+            Object::Variant { .. } => "Variant",
+            Object::Module(_) => "Module",
+        }
+    }
+
+    pub fn is_null(&self) -> bool {
+        self.is_same(&NULL)
+    }
+
+    pub fn is_result(&self) -> bool {
+        match self {
+            Object::Ok(_) | Object::Err(_) => true,
+            _ => false,
+        }
+    }
+
     pub fn is_error(&self) -> bool {
-        self.is_same(&Object::Error(String::new()))
+        match self {
+            Object::Error(_) | Object::Thrown(_) => true,
+            _ => false,
+        }
     }
 
-    pub fn create_hash_key(&self) -> Option<String> {
+    // Values that stop evaluation of the enclosing expression and unwind to the nearest
+    // function call (ReturnValue) or try/catch (Error, Thrown).
+    pub fn is_return_or_error(&self) -> bool {
         match self {
-            Object::Integer(v) => Some(format!("Integer<{}>", v)),
-            Object::Bool(v) => Some(format!("Bool<{}>", v)),
-            Object::StringValue(v) => Some(format!("StringValue<{}>", v)),
+            Object::ReturnValue(_) => true,
+            _ => self.is_error(),
+        }
+    }
+
+    pub fn create_hash_key(&self) -> Option<HashKey> {
+        match self {
+            Object::Integer(v) => Some(HashKey::Integer(*v)),
+            Object::Bool(v) => Some(HashKey::Bool(*v)),
+            Object::StringValue(v) => Some(HashKey::String(v.to_owned())),
+            Object::Array(elements) => {
+                let mut keys = vec![];
+                for e in elements.iter() {
+                    keys.push(e.create_hash_key()?);
+                }
+                Some(HashKey::Array(keys))
+            }
+            Object::Variant {
+                variant_type,
+                payload,
+            } => {
+                let mut keys = vec![];
+                for value in payload.iter() {
+                    keys.push(value.create_hash_key()?);
+                }
+                Some(HashKey::Variant {
+                    enum_name: variant_type.enum_name.to_owned(),
+                    tag: variant_type.tag.to_owned(),
+                    payload: keys,
+                })
+            }
             _ => None,
         }
     }
@@ -79,11 +242,172 @@ impl PartialEq for Object {
                 Object::Error(r) => l == r,
                 _ => false,
             },
+            Object::Thrown(l) => match other {
+                Object::Thrown(r) => l == r,
+                _ => false,
+            },
+            Object::Ok(l) => match other {
+                Object::Ok(r) => l == r,
+                _ => false,
+            },
+            Object::Err(l) => match other {
+                Object::Err(r) => l == r,
+                _ => false,
+            },
+            Object::Array(l) => match other {
+                Object::Array(r) => l == r,
+                _ => false,
+            },
+            // Unlike sets, hashes are ordered: equal hashes hold the same entries in the same
+            // insertion order.
+            Object::Hash(l) => match other {
+                Object::Hash(r) => {
+                    l.len() == r.len()
+                        && l.iter()
+                            .zip(r.iter())
+                            .all(|((lk, lp), (rk, rp))| lk == rk && lp.value == rp.value)
+                }
+                _ => false,
+            },
+            Object::Set(l) => match other {
+                Object::Set(r) => l.len() == r.len() && l.keys().all(|key| r.contains_key(key)),
+                _ => false,
+            },
+            Object::StructType(l) => match other {
+                Object::StructType(r) => Rc::ptr_eq(l, r),
+                _ => false,
+            },
+            Object::Struct {
+                struct_type: lt,
+                values: lv,
+            } => match other {
+                Object::Struct {
+                    struct_type: rt,
+                    values: rv,
+                } => Rc::ptr_eq(lt, rt) && lv == rv,
+                _ => false,
+            },
+            Object::EnumType(l) => match other {
+                Object::EnumType(r) => Rc::ptr_eq(l, r),
+                _ => false,
+            },
+            Object::VariantType(l) => match other {
+                Object::VariantType(r) => Rc::ptr_eq(l, r),
+                _ => false,
+            },
+            Object::Variant {
+                variant_type: lt,
+                payload: lp,
+            } => match other {
+                Object::Variant {
+                    variant_type: rt,
+                    payload: rp,
+                } => lt.enum_name == rt.enum_name && lt.tag == rt.tag && lp == rp,
+                _ => false,
+            },
+            Object::Module(l) => match other {
+                Object::Module(r) => Rc::ptr_eq(l, r),
+                _ => false,
+            },
             _ => false,
         }
     }
 }
 
+impl fmt::Display for Object {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Object::Null => write!(f, "null"),
+            Object::Integer(v) => write!(f, "{}", v),
+            Object::Bool(v) => write!(f, "{}", v),
+            Object::StringValue(v) => write!(f, "{}", v),
+            Object::ReturnValue(v) => write!(f, "{}", v),
+            Object::Error(v) => write!(f, "ERROR: {}", v),
+            Object::Thrown(v) => write!(f, "ERROR: {}", v),
+            Object::Ok(v) => write!(f, "ok({})", v),
+            Object::Err(v) => write!(f, "err({})", v),
+            Object::Function {
+                name,
+                parameters,
+                body: _,
+                environment: _,
+            } => {
+                let parameters = parameters
+                    .iter()
+                    .map(|p| match p.as_ref() {
+                        Node::Identifier { token: _, value, span: _ } => value.to_owned(),
+                        _ => String::new(),
+                    })
+                    .collect::<Vec<String>>();
+                match name {
+                    Some(name) => write!(f, "fn {}({})", name, parameters.join(", ")),
+                    None => write!(f, "fn({})", parameters.join(", ")),
+                }
+            }
+            Object::Builtin(builtin) => write!(f, "builtin {}", builtin.name),
+            Object::Array(elements) => {
+                let elements = elements
+                    .iter()
+                    .map(|e| e.to_string())
+                    .collect::<Vec<String>>();
+                write!(f, "[{}]", elements.join(", "))
+            }
+            Object::Hash(pairs) => {
+                let pairs = pairs
+                    .values()
+                    .map(|p| format!("{}: {}", p.key, p.value))
+                    .collect::<Vec<String>>();
+                write!(f, "{{{}}}", pairs.join(", "))
+            }
+            Object::Set(elements) => {
+                let elements = elements
+                    .values()
+                    .map(|e| e.to_string())
+                    .collect::<Vec<String>>();
+                write!(f, "#{{{}}}", elements.join(", "))
+            }
+            Object::StructType(struct_type) => write!(f, "struct {}", struct_type.name),
+            Object::Struct {
+                struct_type,
+                values,
+            } => {
+                let fields = struct_type
+                    .fields
+                    .iter()
+                    .zip(values.iter())
+                    .map(|(field, value)| format!("{}: {}", field, value))
+                    .collect::<Vec<String>>();
+                if fields.is_empty() {
+                    write!(f, "{} {{}}", struct_type.name)
+                } else {
+                    write!(f, "{} {{ {} }}", struct_type.name, fields.join(", "))
+                }
+            }
+            Object::EnumType(enum_type) => write!(f, "enum {}", enum_type.name),
+            Object::VariantType(variant_type) => write!(
+                f,
+                "variant {}.{}",
+                variant_type.enum_name, variant_type.tag
+            ),
+            Object::Variant {
+                variant_type,
+                payload,
+            } => {
+                if payload.is_empty() {
+                    return write!(f, "{}", variant_type.tag);
+                }
+                let payload = payload
+                    .iter()
+                    .map(|v| v.to_string())
+                    .collect::<Vec<String>>();
+                write!(f, "{}({})", variant_type.tag, payload.join(", "))
+            }
+            Object::Module(module) => write!(f, "module {}", module.path.display()),
+        }
+    }
+}
+
+pub const NULL: Object = Object::Null;
 pub const TRUE: Object = Object::Bool(true);
 pub const FALSE: Object = Object::Bool(false);
 pub const INTEGER: Object = Object::Integer(0);
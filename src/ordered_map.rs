@@ -0,0 +1,114 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::iter::FromIterator;
+
+/// A map that remembers the order its keys were first inserted in. Iteration,
+/// `keys` and `values` follow that order; replacing the value of an existing key
+/// keeps its position, and removing a key closes the gap.
+#[derive(Debug, Clone)]
+pub struct OrderedMap<K, V> {
+    entries: Vec<(K, V)>,
+    indices: HashMap<K, usize>,
+}
+
+impl<K: Hash + Eq + Clone, V> OrderedMap<K, V> {
+    pub fn new() -> OrderedMap<K, V> {
+        OrderedMap {
+            entries: vec![],
+            indices: HashMap::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn get(&self, key: &K) -> Option<&V> {
+        self.indices.get(key).map(|i| &self.entries[*i].1)
+    }
+
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.indices.contains_key(key)
+    }
+
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        if let Some(i) = self.indices.get(&key) {
+            return Some(std::mem::replace(&mut self.entries[*i].1, value));
+        }
+        self.indices.insert(key.clone(), self.entries.len());
+        self.entries.push((key, value));
+        return None;
+    }
+
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        let i = self.indices.remove(key)?;
+        let (_, value) = self.entries.remove(i);
+        for (k, _) in self.entries[i..].iter() {
+            *self.indices.get_mut(k).unwrap() -= 1;
+        }
+        return Some(value);
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> {
+        self.entries.iter().map(|(k, v)| (k, v))
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &K> {
+        self.entries.iter().map(|(k, _)| k)
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &V> {
+        self.entries.iter().map(|(_, v)| v)
+    }
+}
+
+impl<K: Hash + Eq + Clone, V> Default for OrderedMap<K, V> {
+    fn default() -> Self {
+        OrderedMap::new()
+    }
+}
+
+impl<K: Hash + Eq + Clone, V> Extend<(K, V)> for OrderedMap<K, V> {
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
+        for (k, v) in iter {
+            self.insert(k, v);
+        }
+    }
+}
+
+impl<K: Hash + Eq + Clone, V> FromIterator<(K, V)> for OrderedMap<K, V> {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let mut map = OrderedMap::new();
+        map.extend(iter);
+        map
+    }
+}
+
+#[test]
+fn test_insertion_order() {
+    let mut map = OrderedMap::new();
+    map.insert("b", 1);
+    map.insert("a", 2);
+    map.insert("c", 3);
+    assert_eq!(map.keys().cloned().collect::<Vec<&str>>(), vec!["b", "a", "c"]);
+
+    assert_eq!(map.insert("b", 10), Some(1));
+    assert_eq!(map.values().cloned().collect::<Vec<i32>>(), vec![10, 2, 3]);
+}
+
+#[test]
+fn test_remove() {
+    let mut map = vec![("a", 1), ("b", 2), ("c", 3)].into_iter().collect::<OrderedMap<&str, i32>>();
+    assert_eq!(map.remove(&"a"), Some(1));
+    assert_eq!(map.remove(&"a"), None);
+    assert_eq!(map.get(&"c"), Some(&3));
+    assert_eq!(map.iter().map(|(k, v)| (*k, *v)).collect::<Vec<(&str, i32)>>(), vec![("b", 2), ("c", 3)]);
+
+    map.insert("a", 4);
+    assert_eq!(map.keys().cloned().collect::<Vec<&str>>(), vec!["b", "c", "a"]);
+    assert_eq!(map.len(), 3);
+}
@@ -1,6 +1,6 @@
 use crate::ast;
 use crate::lexer::Lexer;
-use crate::token::Token;
+use crate::token::{Position, Span, Token};
 
 use std::collections::HashMap;
 use std::mem;
@@ -12,7 +12,10 @@ pub struct Parser {
     lexer: Lexer,
     cur_token: Token,
     peek_token: Token,
+    cur_span: Span,
+    peek_span: Span,
     errors: Vec<String>,
+    error_positions: Vec<Position>,
     prefix_parse_fns: HashMap<Token, PrefixParseFn>,
     infix_parse_fns: HashMap<Token, InfixParseFn>,
 }
@@ -20,12 +23,17 @@ pub struct Parser {
 impl Parser {
     pub fn new(mut lexer: Lexer) -> Parser {
         let cur_token = lexer.token();
+        let cur_span = lexer.span();
         let peek_token = lexer.token();
+        let peek_span = lexer.span();
         let mut parser = Parser {
             lexer,
             cur_token,
             peek_token,
+            cur_span,
+            peek_span,
             errors: vec![],
+            error_positions: vec![],
             prefix_parse_fns: HashMap::new(),
             infix_parse_fns: HashMap::new(),
         };
@@ -37,11 +45,15 @@ impl Parser {
         parser.register_prefix_parse_fn(Token::Minus, Parser::parse_prefix_expression);
         parser.register_prefix_parse_fn(Token::True, Parser::parse_boolean);
         parser.register_prefix_parse_fn(Token::False, Parser::parse_boolean);
+        parser.register_prefix_parse_fn(Token::Null, Parser::parse_null_literal);
         parser.register_prefix_parse_fn(Token::LParen, Parser::parse_grouped_expression);
         parser.register_prefix_parse_fn(Token::If, Parser::parse_if_expression);
         parser.register_prefix_parse_fn(Token::Function, Parser::parse_function_literal);
+        parser.register_prefix_parse_fn(Token::Try, Parser::parse_try_expression);
+        parser.register_prefix_parse_fn(Token::Match, Parser::parse_match_expression);
         parser.register_prefix_parse_fn(Token::LBracket, Parser::parse_array_literal);
         parser.register_prefix_parse_fn(Token::LBrace, Parser::parse_hash_literal);
+        parser.register_prefix_parse_fn(Token::SetBrace, Parser::parse_set_literal);
 
         parser.register_infix_parse_fn(Token::Plus, Parser::parse_infix_expression);
         parser.register_infix_parse_fn(Token::Minus, Parser::parse_infix_expression);
@@ -53,6 +65,11 @@ impl Parser {
         parser.register_infix_parse_fn(Token::GT, Parser::parse_infix_expression);
         parser.register_infix_parse_fn(Token::LParen, Parser::parse_call_expression);
         parser.register_infix_parse_fn(Token::LBracket, Parser::parse_index_expression);
+        parser.register_infix_parse_fn(Token::Dot, Parser::parse_member_expression);
+        parser.register_infix_parse_fn(Token::Pipe, Parser::parse_pipe_expression);
+        parser.register_infix_parse_fn(Token::NullCoalesce, Parser::parse_infix_expression);
+        parser.register_infix_parse_fn(Token::OptionalDot, Parser::parse_optional_expression);
+        parser.register_infix_parse_fn(Token::Question, Parser::parse_propagate_expression);
 
         return parser;
     }
@@ -61,9 +78,35 @@ impl Parser {
         &self.errors
     }
 
+    /// Where in the source each of `errors` was found.
+    pub fn error_positions(&self) -> &Vec<Position> {
+        &self.error_positions
+    }
+
+    fn error(&mut self, message: String, position: Position) {
+        self.errors.push(message);
+        self.error_positions.push(position);
+    }
+
     fn next_token(&mut self) {
         self.cur_token = self.peek_token.clone();
+        self.cur_span = self.peek_span;
         self.peek_token = self.lexer.token();
+        self.peek_span = self.lexer.span();
+    }
+
+    // Records that `node` runs from `start` to the end of the current token.
+    fn record_span(&self, node: &mut ast::Node, start: Position) {
+        node.set_span(Span {
+            start,
+            end: self.cur_span.end,
+        });
+    }
+
+    fn new_identifier(&mut self) -> Box<ast::Node> {
+        let mut identifier = ast::Node::new_identifier(self.cur_token.clone(), self.cur_token.to_string());
+        self.record_span(&mut identifier, self.cur_span.start);
+        identifier
     }
 
     fn cur_token_is(&self, token: Token) -> bool {
@@ -85,32 +128,47 @@ impl Parser {
     }
 
     fn peek_error(&mut self, token: Token) {
-        self.errors.push(format!(
+        let message = format!(
             "expected next token to be {:?}, got {:?} insted",
             token, self.peek_token
-        ))
+        );
+        self.error(message, self.peek_span.start);
     }
 
     pub fn parse_program(&mut self) -> Option<Box<ast::Node>> {
+        let start = self.cur_span.start;
         let mut statements = vec![];
         while self.cur_token != Token::Eof {
-            println!("cur_token is {:?}", self.cur_token);
             let statement = self.parse_statement();
             if let Some(statement) = statement {
                 statements.push(statement);
             }
             self.next_token();
         }
-        return ast::Node::new_program(statements);
+        let mut program = ast::Node::new_program(statements)?;
+        self.record_span(&mut program, start);
+        return Some(program);
     }
 
     pub(self) fn parse_statement(&mut self) -> Option<Box<ast::Node>> {
+        let start = self.cur_span.start;
         let statement = match self.cur_token {
             Token::Let => self.parse_let_statement(),
             Token::Return => self.parse_return_statement(),
+            Token::Throw => self.parse_throw_statement(),
+            Token::Struct => self.parse_struct_declaration(),
+            Token::Enum => self.parse_enum_declaration(),
+            Token::Impl => self.parse_impl_declaration(),
+            Token::Import => self.parse_import_statement(),
+            Token::Export => self.parse_export_statement(),
+            Token::Function if self.peek_token_is(Token::Ident(String::new())) => {
+                self.parse_function_declaration()
+            }
             _ => self.parse_expression_statement(),
-        };
-        return statement.and_then(|s| ast::Node::new_statement(s));
+        }?;
+        let mut statement = ast::Node::new_statement(statement)?;
+        self.record_span(&mut statement, start);
+        return Some(statement);
     }
 
     pub(self) fn parse_let_statement(&mut self) -> Option<Box<ast::Node>> {
@@ -120,8 +178,7 @@ impl Parser {
             return None;
         }
 
-        let identifier =
-            ast::Node::new_identifier(self.cur_token.clone(), self.cur_token.to_string());
+        let identifier = self.new_identifier();
 
         if !self.expect_peek(Token::Assign) {
             return None;
@@ -160,6 +217,198 @@ impl Parser {
         return ast::Node::new_return_statement(token, expression);
     }
 
+    pub(self) fn parse_throw_statement(&mut self) -> Option<Box<ast::Node>> {
+        let token = self.cur_token.clone();
+
+        self.next_token();
+
+        let expression = self.parse_expression(Precedence::LOWEST)?;
+
+        if self.peek_token_is(Token::SemiColon) {
+            self.next_token();
+        }
+
+        return ast::Node::new_throw_statement(token, expression);
+    }
+
+    pub(self) fn parse_function_declaration(&mut self) -> Option<Box<ast::Node>> {
+        let token = self.cur_token.clone();
+
+        if !self.expect_peek(Token::Ident(String::new())) {
+            return None;
+        }
+
+        let name = self.new_identifier();
+
+        if !self.expect_peek(Token::LParen) {
+            return None;
+        }
+
+        let parameters = self.parse_function_parameters()?;
+
+        if !self.expect_peek(Token::LBrace) {
+            return None;
+        }
+
+        let body = self.parse_block_statement()?;
+
+        return ast::Node::new_function_declaration(token, name, parameters, body);
+    }
+
+    pub(self) fn parse_struct_declaration(&mut self) -> Option<Box<ast::Node>> {
+        let token = self.cur_token.clone();
+
+        if !self.expect_peek(Token::Ident(String::new())) {
+            return None;
+        }
+
+        let name = self.new_identifier();
+
+        if !self.expect_peek(Token::LBrace) {
+            return None;
+        }
+
+        let mut fields = vec![];
+
+        while !self.peek_token_is(Token::RBrace) {
+            if !self.expect_peek(Token::Ident(String::new())) {
+                return None;
+            }
+
+            fields.push(self.new_identifier());
+
+            if !self.peek_token_is(Token::RBrace) && !self.expect_peek(Token::Comma) {
+                return None;
+            }
+        }
+
+        if !self.expect_peek(Token::RBrace) {
+            return None;
+        }
+
+        return ast::Node::new_struct_declaration(token, name, fields);
+    }
+
+    pub(self) fn parse_impl_declaration(&mut self) -> Option<Box<ast::Node>> {
+        let token = self.cur_token.clone();
+
+        if !self.expect_peek(Token::Ident(String::new())) {
+            return None;
+        }
+
+        let name = self.new_identifier();
+
+        if !self.expect_peek(Token::LBrace) {
+            return None;
+        }
+
+        let mut methods = vec![];
+
+        while !self.peek_token_is(Token::RBrace) {
+            if !self.expect_peek(Token::Function) {
+                return None;
+            }
+
+            methods.push(self.parse_function_declaration()?);
+        }
+
+        if !self.expect_peek(Token::RBrace) {
+            return None;
+        }
+
+        return ast::Node::new_impl_declaration(token, name, methods);
+    }
+
+    pub(self) fn parse_import_statement(&mut self) -> Option<Box<ast::Node>> {
+        let token = self.cur_token.clone();
+
+        if !self.expect_peek(Token::Str(String::new())) {
+            return None;
+        }
+
+        let path = ast::Node::new_string_literal(self.cur_token.clone(), self.cur_token.to_string());
+
+        if !self.expect_peek(Token::As) {
+            return None;
+        }
+
+        if !self.expect_peek(Token::Ident(String::new())) {
+            return None;
+        }
+
+        let name = self.new_identifier();
+
+        if !self.expect_peek(Token::SemiColon) {
+            return None;
+        }
+
+        return ast::Node::new_import_statement(token, path, name);
+    }
+
+    pub(self) fn parse_export_statement(&mut self) -> Option<Box<ast::Node>> {
+        let token = self.cur_token.clone();
+
+        self.next_token();
+
+        let statement = match self.cur_token {
+            Token::Let => self.parse_let_statement()?,
+            Token::Function => self.parse_function_declaration()?,
+            _ => {
+                let message = format!("expected let or fn after export, got {:?}", self.cur_token);
+                self.error(message, self.cur_span.start);
+                return None;
+            }
+        };
+
+        return ast::Node::new_export_statement(token, statement);
+    }
+
+    pub(self) fn parse_enum_declaration(&mut self) -> Option<Box<ast::Node>> {
+        let token = self.cur_token.clone();
+
+        if !self.expect_peek(Token::Ident(String::new())) {
+            return None;
+        }
+
+        let name = self.new_identifier();
+
+        if !self.expect_peek(Token::LBrace) {
+            return None;
+        }
+
+        let mut variants = vec![];
+
+        while !self.peek_token_is(Token::RBrace) {
+            if !self.expect_peek(Token::Ident(String::new())) {
+                return None;
+            }
+
+            let variant_token = self.cur_token.clone();
+            let variant_start = self.cur_span.start;
+            let variant_name = self.new_identifier();
+
+            let mut fields = vec![];
+            if self.peek_token_is(Token::LParen) {
+                self.next_token();
+                fields = self.parse_function_parameters()?;
+            }
+
+            let mut variant = ast::Node::new_enum_variant(variant_token, variant_name, fields)?;
+            self.record_span(&mut variant, variant_start);
+            variants.push(variant);
+
+            if !self.peek_token_is(Token::RBrace) && !self.expect_peek(Token::Comma) {
+                return None;
+            }
+        }
+
+        if !self.expect_peek(Token::RBrace) {
+            return None;
+        }
+
+        return ast::Node::new_enum_declaration(token, name, variants);
+    }
+
     pub(self) fn parse_expression_statement(&mut self) -> Option<Box<ast::Node>> {
         let token = self.cur_token.clone();
 
@@ -179,9 +428,10 @@ impl Parser {
 
     pub(self) fn parse_expression(&mut self, precedence: Precedence) -> Option<Box<ast::Node>> {
         let prefix_parse_fn = self.get_prefix_parse_fn(self.cur_token.clone())?;
+        let start = self.cur_span.start;
 
-        let mut left = prefix_parse_fn(self)?;
-        left = ast::Node::new_expression(left)?;
+        let mut left = ast::Node::new_expression(prefix_parse_fn(self)?)?;
+        self.record_span(&mut left, start);
 
         while !self.peek_token_is(Token::SemiColon) && precedence < self.peek_precedence() {
             let infix_parse_fn = self.get_infix_parse_fn(self.peek_token.clone());
@@ -193,18 +443,63 @@ impl Parser {
 
             self.next_token();
 
-            left = infix_parse_fn(self, left)?;
-            left = ast::Node::new_expression(left)?;
+            left = ast::Node::new_expression(infix_parse_fn(self, left)?)?;
+            self.record_span(&mut left, start);
         }
 
         return Some(left);
     }
 
     pub(self) fn parse_identifier(&mut self) -> Option<Box<ast::Node>> {
-        Some(ast::Node::new_identifier(
-            self.cur_token.clone(),
-            self.cur_token.to_string(),
-        ))
+        let is_type_name = self
+            .cur_token
+            .to_string()
+            .starts_with(|c: char| c.is_ascii_uppercase());
+        if is_type_name && self.peek_token_is(Token::LBrace) {
+            return self.parse_struct_literal();
+        }
+
+        Some(self.new_identifier())
+    }
+
+    // `Point { x: 1, y: 2 }`; only capitalized names start a struct literal so that an
+    // identifier followed by a block is left alone.
+    pub(self) fn parse_struct_literal(&mut self) -> Option<Box<ast::Node>> {
+        let token = self.cur_token.clone();
+        let name = self.new_identifier();
+
+        self.next_token();
+
+        let mut fields = vec![];
+        let mut values = vec![];
+
+        while !self.peek_token_is(Token::RBrace) {
+            if !self.expect_peek(Token::Ident(String::new())) {
+                return None;
+            }
+
+            fields.push(self.new_identifier());
+
+            if !self.expect_peek(Token::Colon) {
+                return None;
+            }
+
+            self.next_token();
+
+            let expression = self.parse_expression(Precedence::LOWEST);
+            let value = expression.and_then(|e| ast::Node::new_expression(e))?;
+            values.push(value);
+
+            if !self.peek_token_is(Token::RBrace) && !self.expect_peek(Token::Comma) {
+                return None;
+            }
+        }
+
+        if !self.expect_peek(Token::RBrace) {
+            return None;
+        }
+
+        return ast::Node::new_struct_literal(token, name, fields, values);
     }
 
     pub(self) fn parse_integer_literal(&mut self) -> Option<Box<ast::Node>> {
@@ -240,9 +535,16 @@ impl Parser {
         )
     }
 
+    pub(self) fn parse_set_literal(&mut self) -> Option<Box<ast::Node>> {
+        let token = self.cur_token.clone();
+
+        let elements = self.parse_expression_list(Token::RBrace)?;
+
+        ast::Node::new_set_literal(token, elements)
+    }
+
     pub(self) fn parse_hash_literal(&mut self) -> Option<Box<ast::Node>> {
         let token = self.cur_token.clone();
-        println!("[parse_hash_literal] start token {:?}", token);
 
         let mut elements = vec![];
 
@@ -285,6 +587,10 @@ impl Parser {
         ))
     }
 
+    pub(self) fn parse_null_literal(&mut self) -> Option<Box<ast::Node>> {
+        Some(ast::Node::new_null_literal(self.cur_token.clone()))
+    }
+
     pub(self) fn parse_prefix_expression(&mut self) -> Option<Box<ast::Node>> {
         let token = self.cur_token.clone();
         let operator = self.cur_token.to_string();
@@ -315,6 +621,36 @@ impl Parser {
         return ast::Node::new_infix_expression(token, left, operator, right.unwrap());
     }
 
+    // `left |> f(args)` is sugar for `f(left, args)`, and `left |> f` for `f(left)`.
+    pub(self) fn parse_pipe_expression(&mut self, left: Box<ast::Node>) -> Option<Box<ast::Node>> {
+        let token = self.cur_token.clone();
+
+        let precedence = self.cur_precedence();
+        self.next_token();
+        let right = self.parse_expression(precedence)?;
+
+        let right = match *right {
+            ast::Node::Expression { node, span: _ } => node,
+            node => Box::new(node),
+        };
+
+        match *right {
+            ast::Node::CallExpression {
+                token,
+                function,
+                arguments,
+            } => {
+                let mut arguments = arguments;
+                arguments.insert(0, left);
+                ast::Node::new_call_expression(token, function, arguments)
+            }
+            node => {
+                let function = ast::Node::new_expression(Box::new(node))?;
+                ast::Node::new_call_expression(token, function, vec![left])
+            }
+        }
+    }
+
     pub(self) fn parse_grouped_expression(&mut self) -> Option<Box<ast::Node>> {
         self.next_token();
 
@@ -324,8 +660,6 @@ impl Parser {
             return None;
         }
 
-        eprintln!("grouped expression is {:?}", exp);
-
         return exp;
     }
 
@@ -373,8 +707,142 @@ impl Parser {
         return ast::Node::new_if_expression(token, condition, consequence, alternative);
     }
 
+    // `match (subject) { pattern => result, ... }` where each result is an expression or
+    // a block. Patterns are parsed as expressions and interpreted by the evaluator.
+    pub(self) fn parse_match_expression(&mut self) -> Option<Box<ast::Node>> {
+        let token = self.cur_token.clone();
+
+        if !self.expect_peek(Token::LParen) {
+            return None;
+        }
+
+        self.next_token();
+        let subject = self.parse_expression(Precedence::LOWEST)?;
+
+        if !self.expect_peek(Token::RParen) {
+            return None;
+        }
+
+        if !self.expect_peek(Token::LBrace) {
+            return None;
+        }
+
+        let mut patterns = vec![];
+        let mut results = vec![];
+
+        while !self.peek_token_is(Token::RBrace) {
+            self.next_token();
+
+            let expression = self.parse_expression(Precedence::LOWEST);
+            let pattern = expression.and_then(|e| ast::Node::new_expression(e))?;
+
+            if !self.expect_peek(Token::FatArrow) {
+                return None;
+            }
+
+            let result = if self.peek_token_is(Token::LBrace) && !self.peek_hash_literal() {
+                self.next_token();
+                self.parse_block_statement()?
+            } else {
+                self.next_token();
+                let expression = self.parse_expression(Precedence::LOWEST);
+                expression.and_then(|e| ast::Node::new_expression(e))?
+            };
+
+            patterns.push(pattern);
+            results.push(result);
+
+            if !self.peek_token_is(Token::RBrace) && !self.expect_peek(Token::Comma) {
+                return None;
+            }
+        }
+
+        if !self.expect_peek(Token::RBrace) {
+            return None;
+        }
+
+        return ast::Node::new_match_expression(token, subject, patterns, results);
+    }
+
+    pub(self) fn parse_try_expression(&mut self) -> Option<Box<ast::Node>> {
+        let token = self.cur_token.clone();
+
+        if !self.expect_peek(Token::LBrace) {
+            return None;
+        }
+
+        let block = self.parse_block_statement()?;
+
+        let mut parameter = None;
+        let mut handler = None;
+        if self.peek_token_is(Token::Catch) {
+            self.next_token();
+
+            if !self.expect_peek(Token::LParen) {
+                return None;
+            }
+
+            if !self.expect_peek(Token::Ident(String::new())) {
+                return None;
+            }
+
+            parameter = Some(self.new_identifier());
+
+            if !self.expect_peek(Token::RParen) {
+                return None;
+            }
+
+            if !self.expect_peek(Token::LBrace) {
+                return None;
+            }
+
+            handler = Some(self.parse_block_statement()?);
+        }
+
+        let mut finalizer = None;
+        if self.peek_token_is(Token::Finally) {
+            self.next_token();
+
+            if !self.expect_peek(Token::LBrace) {
+                return None;
+            }
+
+            finalizer = Some(self.parse_block_statement()?);
+        }
+
+        if handler.is_none() && finalizer.is_none() {
+            self.error(
+                "expected catch or finally after try block".to_string(),
+                self.cur_span.start,
+            );
+            return None;
+        }
+
+        return ast::Node::new_try_expression(token, block, parameter, handler, finalizer);
+    }
+
+    // Whether the `{` in the peek token opens a hash literal rather than a block: it does
+    // when a `:` comes before the end of the first statement, so `{}` is an empty block.
+    fn peek_hash_literal(&mut self) -> bool {
+        let mut depth = 0;
+        self.lexer.scan_ahead(|token| match token {
+            Token::LParen | Token::LBracket | Token::LBrace | Token::SetBrace => {
+                depth += 1;
+                None
+            }
+            Token::RParen | Token::RBracket | Token::RBrace if depth > 0 => {
+                depth -= 1;
+                None
+            }
+            Token::Colon if depth == 0 => Some(true),
+            Token::RBrace | Token::SemiColon | Token::Eof => Some(false),
+            _ => None,
+        })
+    }
+
     pub(self) fn parse_block_statement(&mut self) -> Option<Box<ast::Node>> {
         let token = self.cur_token.clone();
+        let start = self.cur_span.start;
         let mut statements = vec![];
 
         self.next_token();
@@ -387,7 +855,9 @@ impl Parser {
             self.next_token();
         }
 
-        return ast::Node::new_block_statement(token, statements);
+        let mut block = ast::Node::new_block_statement(token, statements)?;
+        self.record_span(&mut block, start);
+        return Some(block);
     }
 
     pub(self) fn parse_function_literal(&mut self) -> Option<Box<ast::Node>> {
@@ -428,18 +898,12 @@ impl Parser {
 
         self.next_token();
 
-        identifiers.push(ast::Node::new_identifier(
-            self.cur_token.clone(),
-            self.cur_token.to_string(),
-        ));
+        identifiers.push(self.new_identifier());
 
         while self.peek_token_is(Token::Comma) {
             self.next_token();
             self.next_token();
-            identifiers.push(ast::Node::new_identifier(
-                self.cur_token.clone(),
-                self.cur_token.to_string(),
-            ));
+            identifiers.push(self.new_identifier());
         }
 
         if !self.expect_peek(Token::RParen) {
@@ -502,8 +966,17 @@ impl Parser {
         let token = self.cur_token.clone();
 
         self.next_token();
+        if self.cur_token_is(Token::Colon) {
+            return self.parse_slice_expression(token, left, None);
+        }
+
         let index = self.parse_expression(Precedence::LOWEST)?;
 
+        if self.peek_token_is(Token::Colon) {
+            self.next_token();
+            return self.parse_slice_expression(token, left, Some(index));
+        }
+
         if !self.expect_peek(Token::RBracket) {
             return None;
         }
@@ -511,6 +984,77 @@ impl Parser {
         return ast::Node::new_index_expression(token, left, index);
     }
 
+    // Called with the `:` of `left[start:end]` as the current token.
+    pub(self) fn parse_slice_expression(
+        &mut self,
+        token: Token,
+        left: Box<ast::Node>,
+        start: Option<Box<ast::Node>>,
+    ) -> Option<Box<ast::Node>> {
+        let mut end = None;
+        if !self.peek_token_is(Token::RBracket) {
+            self.next_token();
+            end = Some(self.parse_expression(Precedence::LOWEST)?);
+        }
+
+        if !self.expect_peek(Token::RBracket) {
+            return None;
+        }
+
+        return ast::Node::new_slice_expression(token, left, start, end);
+    }
+
+    pub(self) fn parse_member_expression(
+        &mut self,
+        left: Box<ast::Node>,
+    ) -> Option<Box<ast::Node>> {
+        let token = self.cur_token.clone();
+
+        if !self.expect_peek(Token::Ident(String::new())) {
+            return None;
+        }
+
+        let member = self.new_identifier();
+
+        return ast::Node::new_member_expression(token, left, member);
+    }
+
+    pub(self) fn parse_propagate_expression(
+        &mut self,
+        left: Box<ast::Node>,
+    ) -> Option<Box<ast::Node>> {
+        ast::Node::new_propagate_expression(self.cur_token.clone(), left)
+    }
+
+    // `left?.member` and `left?.[index]` keep the OptionalDot token so evaluation can
+    // short-circuit to null when `left` is null, or propagate when `left` is a result.
+    pub(self) fn parse_optional_expression(
+        &mut self,
+        left: Box<ast::Node>,
+    ) -> Option<Box<ast::Node>> {
+        let token = self.cur_token.clone();
+
+        if self.peek_token_is(Token::LBracket) {
+            self.next_token();
+            self.next_token();
+            let index = self.parse_expression(Precedence::LOWEST)?;
+
+            if !self.expect_peek(Token::RBracket) {
+                return None;
+            }
+
+            return ast::Node::new_index_expression(token, left, index);
+        }
+
+        if !self.expect_peek(Token::Ident(String::new())) {
+            return None;
+        }
+
+        let member = self.new_identifier();
+
+        return ast::Node::new_member_expression(token, left, member);
+    }
+
     fn peek_precedence(&self) -> Precedence {
         self.precedence(&self.peek_token)
     }
@@ -521,6 +1065,8 @@ impl Parser {
 
     fn precedence(&self, token: &Token) -> Precedence {
         match token {
+            Token::Pipe => Precedence::PIPE,
+            Token::NullCoalesce => Precedence::COALESCE,
             Token::Eq => Precedence::EQUALS,
             Token::NotEq => Precedence::EQUALS,
             Token::LT => Precedence::LESSGREATER,
@@ -531,6 +1077,9 @@ impl Parser {
             Token::Asterisk => Precedence::PRODUCT,
             Token::LParen => Precedence::CALL,
             Token::LBracket => Precedence::INDEX,
+            Token::Dot => Precedence::INDEX,
+            Token::OptionalDot => Precedence::INDEX,
+            Token::Question => Precedence::INDEX,
             _ => Precedence::LOWEST,
         }
     }
@@ -565,6 +1114,8 @@ impl Parser {
 #[derive(PartialOrd, PartialEq)]
 enum Precedence {
     LOWEST,
+    PIPE,
+    COALESCE,
     EQUALS,
     LESSGREATER,
     SUM,
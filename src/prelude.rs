@@ -0,0 +1,70 @@
+use crate::environment::Environment;
+use crate::evaluator::eval;
+use crate::lexer::Lexer;
+use crate::parser::Parser;
+
+/// Monkey source of the functions every program can use without defining them.
+/// It is empty for now: `map`, `reduce`, `filter`, `range` and `contains` are builtins,
+/// and a Monkey definition would shadow them with a slower, recursive version.
+pub const PRELUDE: &str = include_str!("prelude.monkey");
+
+/// Evaluates the prelude into `env`. The error names the prelude line that failed to parse.
+pub fn load(env: &mut Environment) -> Result<(), String> {
+    load_source(env, PRELUDE)
+}
+
+pub(crate) fn load_source(env: &mut Environment, source: &str) -> Result<(), String> {
+    let lexer = Lexer::new(source.to_string());
+    let mut parser = Parser::new(lexer);
+    let program = parser.parse_program();
+    if let Some(position) = parser.error_positions().first() {
+        return Err(format!(
+            "failed to parse prelude at line {}: {}",
+            position.line,
+            parser.errors()[0]
+        ));
+    }
+
+    let program = program.ok_or_else(|| "failed to parse prelude".to_owned())?;
+    match eval(&program, env) {
+        Some(result) if result.is_error() => Err(format!("failed to load prelude: {}", result)),
+        _ => Ok(()),
+    }
+}
+
+#[test]
+fn test_load_source() {
+    let mut env = Environment::new();
+    assert_eq!(load_source(&mut env, "fn twice(x) { x * 2 }"), Ok(()));
+    assert!(env.get("twice").is_some());
+
+    let source = "fn one() { 1 }\n\nfn two(a b) { 2 }\n";
+    assert_eq!(
+        load_source(&mut Environment::new(), source),
+        Err("failed to parse prelude at line 3: expected next token to be RParen, got Ident(\"b\") insted".to_owned())
+    );
+    assert_eq!(
+        load_source(&mut Environment::new(), "let x = missing;"),
+        Err("failed to load prelude: ERROR: identifier not found: \"missing\"".to_owned())
+    );
+}
+
+#[test]
+fn test_host_functions_replace_prelude_functions() {
+    use crate::object::{Object, Value};
+    use std::rc::Rc;
+
+    let run = |env: &mut Environment, source: &str| {
+        let mut parser = Parser::new(Lexer::new(source.to_string()));
+        let program = parser.parse_program().unwrap();
+        eval(&program, env).unwrap().to_string()
+    };
+
+    let mut env = Environment::new_with_prelude_source(r#"fn map(arr, f) { "prelude" }"#).unwrap();
+    assert_eq!(run(&mut env, "map([1], len)"), "prelude");
+    env.register_fn("map", |_: &[Value]| Ok(Rc::new(Object::StringValue("host".to_owned()))));
+    assert_eq!(run(&mut env, "map([1], len)"), "host");
+    env.remove_fn("map");
+    assert_eq!(run(&mut env, "map([1], len)"), "ERROR: identifier not found: \"map\"");
+    assert_eq!(run(&mut env, r#"let map = fn(a, f) { "script" }; map([1], len)"#), "script");
+}
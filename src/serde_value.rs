@@ -0,0 +1,759 @@
+//! Serde support for Monkey values, enabled by the `serde` feature.
+//!
+//! `Object` implements `Serialize` and `Deserialize`, so values can be written and read by
+//! any serde format. `to_value` and `from_value` convert between Monkey values and any Rust
+//! type that implements the serde traits, such as a host's config struct.
+//!
+//! Enums use serde's externally tagged representation: a unit variant is its name as a
+//! string, any other variant a hash with the name as its only key. Monkey has no floating
+//! point numbers, so floats are rejected.
+
+use crate::native::Error;
+use crate::object::{HashKey, HashPair, Object, Value};
+use crate::ordered_map::OrderedMap;
+
+use serde::de::{self, DeserializeOwned, IntoDeserializer};
+use serde::ser::{self, SerializeMap, SerializeSeq};
+use serde::{forward_to_deserialize_any, Deserialize, Deserializer, Serialize, Serializer};
+use std::convert::TryFrom;
+use std::fmt;
+use std::rc::Rc;
+
+impl ser::Error for Error {
+    fn custom<T: fmt::Display>(message: T) -> Error {
+        Error::new(message.to_string())
+    }
+}
+
+impl de::Error for Error {
+    fn custom<T: fmt::Display>(message: T) -> Error {
+        Error::new(message.to_string())
+    }
+}
+
+/// Converts any serializable Rust value to a Monkey value.
+pub fn to_value<T: Serialize + ?Sized>(value: &T) -> Result<Value, Error> {
+    value.serialize(ValueSerializer)
+}
+
+/// Converts a Monkey value to any deserializable Rust value.
+pub fn from_value<T: DeserializeOwned>(value: &Value) -> Result<T, Error> {
+    T::deserialize(value.as_ref())
+}
+
+fn new_value(object: Object) -> Value {
+    Rc::new(object)
+}
+
+fn insert_pair(pairs: &mut OrderedMap<HashKey, HashPair>, key: Value, value: Value) -> Result<(), Error> {
+    let hash_key = key
+        .create_hash_key()
+        .ok_or_else(|| Error::new(format!("unusable as hash key: {}", key.type_name())))?;
+    pairs.insert(hash_key, HashPair { key, value });
+    Ok(())
+}
+
+// A hash with the variant name as its only key, for every variant that is not a unit.
+fn tagged(variant: &str, value: Value) -> Value {
+    let mut pairs = OrderedMap::new();
+    pairs.insert(
+        HashKey::String(variant.to_owned()),
+        HashPair {
+            key: new_value(Object::StringValue(variant.to_owned())),
+            value,
+        },
+    );
+    new_value(Object::Hash(pairs))
+}
+
+impl Serialize for Object {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Object::Null => serializer.serialize_unit(),
+            Object::Integer(v) => serializer.serialize_i64(*v),
+            Object::Bool(v) => serializer.serialize_bool(*v),
+            Object::StringValue(v) => serializer.serialize_str(v),
+            Object::ReturnValue(v) => v.serialize(serializer),
+            Object::Array(elements) => serializer.collect_seq(elements.iter()),
+            Object::Set(elements) => serializer.collect_seq(elements.values()),
+            Object::Hash(pairs) => {
+                let mut map = serializer.serialize_map(Some(pairs.len()))?;
+                for pair in pairs.values() {
+                    map.serialize_entry(&pair.key, &pair.value)?;
+                }
+                map.end()
+            }
+            Object::Struct {
+                struct_type,
+                values,
+            } => {
+                let mut map = serializer.serialize_map(Some(values.len()))?;
+                for (field, value) in struct_type.fields.iter().zip(values.iter()) {
+                    map.serialize_entry(field, value)?;
+                }
+                map.end()
+            }
+            Object::Variant {
+                variant_type,
+                payload,
+            } => match payload.len() {
+                0 => serializer.serialize_str(&variant_type.tag),
+                1 => {
+                    let mut map = serializer.serialize_map(Some(1))?;
+                    map.serialize_entry(&variant_type.tag, &payload[0])?;
+                    map.end()
+                }
+                _ => {
+                    let mut map = serializer.serialize_map(Some(1))?;
+                    map.serialize_entry(&variant_type.tag, payload)?;
+                    map.end()
+                }
+            },
+            o => Err(ser::Error::custom(format!("cannot serialize {}", o.type_name()))),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Object {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Object, D::Error> {
+        deserializer.deserialize_any(ObjectVisitor)
+    }
+}
+
+struct ObjectVisitor;
+
+impl<'de> de::Visitor<'de> for ObjectVisitor {
+    type Value = Object;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a value Monkey can represent")
+    }
+
+    fn visit_bool<E>(self, v: bool) -> Result<Object, E> {
+        Ok(Object::Bool(v))
+    }
+
+    fn visit_i64<E>(self, v: i64) -> Result<Object, E> {
+        Ok(Object::Integer(v))
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<Object, E> {
+        i64::try_from(v)
+            .map(Object::Integer)
+            .map_err(|_| E::custom(format!("integer out of range: {}", v)))
+    }
+
+    fn visit_f64<E: de::Error>(self, v: f64) -> Result<Object, E> {
+        Err(E::custom(format!("floating point numbers are not supported, got = {}", v)))
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Object, E> {
+        Ok(Object::StringValue(v.to_owned()))
+    }
+
+    fn visit_string<E>(self, v: String) -> Result<Object, E> {
+        Ok(Object::StringValue(v))
+    }
+
+    fn visit_bytes<E>(self, v: &[u8]) -> Result<Object, E> {
+        Ok(Object::Array(
+            v.iter().map(|b| new_value(Object::Integer(i64::from(*b)))).collect(),
+        ))
+    }
+
+    fn visit_unit<E>(self) -> Result<Object, E> {
+        Ok(Object::Null)
+    }
+
+    fn visit_none<E>(self) -> Result<Object, E> {
+        Ok(Object::Null)
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<Object, D::Error> {
+        Object::deserialize(deserializer)
+    }
+
+    fn visit_newtype_struct<D: Deserializer<'de>>(self, deserializer: D) -> Result<Object, D::Error> {
+        Object::deserialize(deserializer)
+    }
+
+    fn visit_seq<A: de::SeqAccess<'de>>(self, mut seq: A) -> Result<Object, A::Error> {
+        let mut elements = vec![];
+        while let Some(element) = seq.next_element::<Object>()? {
+            elements.push(new_value(element));
+        }
+        Ok(Object::Array(elements))
+    }
+
+    fn visit_map<A: de::MapAccess<'de>>(self, mut map: A) -> Result<Object, A::Error> {
+        let mut pairs = OrderedMap::new();
+        while let Some((key, value)) = map.next_entry::<Object, Object>()? {
+            insert_pair(&mut pairs, new_value(key), new_value(value)).map_err(de::Error::custom)?;
+        }
+        Ok(Object::Hash(pairs))
+    }
+}
+
+struct ValueSerializer;
+
+impl Serializer for ValueSerializer {
+    type Ok = Value;
+    type Error = Error;
+    type SerializeSeq = SeqSerializer;
+    type SerializeTuple = SeqSerializer;
+    type SerializeTupleStruct = SeqSerializer;
+    type SerializeTupleVariant = SeqSerializer;
+    type SerializeMap = MapSerializer;
+    type SerializeStruct = MapSerializer;
+    type SerializeStructVariant = MapSerializer;
+
+    fn serialize_bool(self, v: bool) -> Result<Value, Error> {
+        Ok(new_value(Object::Bool(v)))
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Value, Error> {
+        self.serialize_i64(i64::from(v))
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Value, Error> {
+        self.serialize_i64(i64::from(v))
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Value, Error> {
+        self.serialize_i64(i64::from(v))
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Value, Error> {
+        Ok(new_value(Object::Integer(v)))
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Value, Error> {
+        self.serialize_i64(i64::from(v))
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Value, Error> {
+        self.serialize_i64(i64::from(v))
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Value, Error> {
+        self.serialize_i64(i64::from(v))
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Value, Error> {
+        let v = i64::try_from(v).map_err(|_| Error::new(format!("integer out of range: {}", v)))?;
+        self.serialize_i64(v)
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Value, Error> {
+        self.serialize_f64(f64::from(v))
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Value, Error> {
+        Err(Error::new(format!("floating point numbers are not supported, got = {}", v)))
+    }
+
+    fn serialize_char(self, v: char) -> Result<Value, Error> {
+        Ok(new_value(Object::StringValue(v.to_string())))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Value, Error> {
+        Ok(new_value(Object::StringValue(v.to_owned())))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Value, Error> {
+        Ok(new_value(Object::Array(
+            v.iter().map(|b| new_value(Object::Integer(i64::from(*b)))).collect(),
+        )))
+    }
+
+    fn serialize_none(self) -> Result<Value, Error> {
+        self.serialize_unit()
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<Value, Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Value, Error> {
+        Ok(new_value(Object::Null))
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Value, Error> {
+        self.serialize_unit()
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+    ) -> Result<Value, Error> {
+        self.serialize_str(variant)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<Value, Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<Value, Error> {
+        Ok(tagged(variant, to_value(value)?))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<SeqSerializer, Error> {
+        Ok(SeqSerializer {
+            variant: None,
+            elements: Vec::with_capacity(len.unwrap_or(0)),
+        })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<SeqSerializer, Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, len: usize) -> Result<SeqSerializer, Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<SeqSerializer, Error> {
+        Ok(SeqSerializer {
+            variant: Some(variant),
+            elements: Vec::with_capacity(len),
+        })
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<MapSerializer, Error> {
+        Ok(MapSerializer {
+            variant: None,
+            pairs: OrderedMap::new(),
+            next_key: None,
+        })
+    }
+
+    fn serialize_struct(self, _name: &'static str, len: usize) -> Result<MapSerializer, Error> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<MapSerializer, Error> {
+        Ok(MapSerializer {
+            variant: Some(variant),
+            pairs: OrderedMap::new(),
+            next_key: None,
+        })
+    }
+}
+
+// Builds an array; for a tuple variant, the array is tagged with the variant name.
+struct SeqSerializer {
+    variant: Option<&'static str>,
+    elements: Vec<Value>,
+}
+
+impl SeqSerializer {
+    fn finish(self) -> Value {
+        let array = new_value(Object::Array(self.elements));
+        match self.variant {
+            Some(variant) => tagged(variant, array),
+            None => array,
+        }
+    }
+}
+
+impl ser::SerializeSeq for SeqSerializer {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.elements.push(to_value(value)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<Value, Error> {
+        Ok(self.finish())
+    }
+}
+
+impl ser::SerializeTuple for SeqSerializer {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<Value, Error> {
+        Ok(self.finish())
+    }
+}
+
+impl ser::SerializeTupleStruct for SeqSerializer {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<Value, Error> {
+        Ok(self.finish())
+    }
+}
+
+impl ser::SerializeTupleVariant for SeqSerializer {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<Value, Error> {
+        Ok(self.finish())
+    }
+}
+
+// Builds a hash; for a struct variant, the hash is tagged with the variant name.
+struct MapSerializer {
+    variant: Option<&'static str>,
+    pairs: OrderedMap<HashKey, HashPair>,
+    next_key: Option<Value>,
+}
+
+impl MapSerializer {
+    fn finish(self) -> Value {
+        let hash = new_value(Object::Hash(self.pairs));
+        match self.variant {
+            Some(variant) => tagged(variant, hash),
+            None => hash,
+        }
+    }
+}
+
+impl ser::SerializeMap for MapSerializer {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), Error> {
+        self.next_key = Some(to_value(key)?);
+        Ok(())
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        let key = self
+            .next_key
+            .take()
+            .ok_or_else(|| Error::new("serialize_value called before serialize_key"))?;
+        insert_pair(&mut self.pairs, key, to_value(value)?)
+    }
+
+    fn end(self) -> Result<Value, Error> {
+        Ok(self.finish())
+    }
+}
+
+impl ser::SerializeStruct for MapSerializer {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, key: &'static str, value: &T) -> Result<(), Error> {
+        SerializeMap::serialize_entry(self, key, value)
+    }
+
+    fn end(self) -> Result<Value, Error> {
+        Ok(self.finish())
+    }
+}
+
+impl ser::SerializeStructVariant for MapSerializer {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, key: &'static str, value: &T) -> Result<(), Error> {
+        SerializeMap::serialize_entry(self, key, value)
+    }
+
+    fn end(self) -> Result<Value, Error> {
+        Ok(self.finish())
+    }
+}
+
+impl<'de> Deserializer<'de> for &'de Object {
+    type Error = Error;
+
+    fn deserialize_any<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self {
+            Object::Null => visitor.visit_unit(),
+            Object::Integer(v) => visitor.visit_i64(*v),
+            Object::Bool(v) => visitor.visit_bool(*v),
+            Object::StringValue(v) => visitor.visit_borrowed_str(v),
+            Object::ReturnValue(v) => v.as_ref().deserialize_any(visitor),
+            Object::Array(elements) => visit_seq(elements.iter(), visitor),
+            Object::Set(elements) => visit_seq(elements.values(), visitor),
+            Object::Hash(pairs) => visitor.visit_map(MapAccess {
+                entries: pairs
+                    .values()
+                    .map(|pair| (Key::Value(&pair.key), Content::Value(&pair.value)))
+                    .collect::<Vec<_>>()
+                    .into_iter(),
+                value: None,
+            }),
+            Object::Struct {
+                struct_type,
+                values,
+            } => visitor.visit_map(MapAccess {
+                entries: struct_type
+                    .fields
+                    .iter()
+                    .zip(values.iter())
+                    .map(|(field, value)| (Key::Name(field), Content::Value(value)))
+                    .collect::<Vec<_>>()
+                    .into_iter(),
+                value: None,
+            }),
+            Object::Variant {
+                variant_type,
+                payload,
+            } => {
+                let content = match payload.len() {
+                    0 => return visitor.visit_borrowed_str(&variant_type.tag),
+                    1 => Content::Value(&payload[0]),
+                    _ => Content::Payload(payload),
+                };
+                visitor.visit_map(MapAccess {
+                    entries: vec![(Key::Name(&variant_type.tag), content)].into_iter(),
+                    value: None,
+                })
+            }
+            o => Err(Error::new(format!("cannot deserialize {}", o.type_name()))),
+        }
+    }
+
+    fn deserialize_option<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self {
+            Object::Null => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: de::Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: de::Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        match self {
+            Object::StringValue(v) => visitor.visit_enum(v.as_str().into_deserializer()),
+            Object::Hash(pairs) if pairs.len() == 1 => {
+                let pair = pairs.values().next().unwrap();
+                visitor.visit_enum(EnumAccess {
+                    variant: Key::Value(&pair.key),
+                    content: Content::Value(&pair.value),
+                })
+            }
+            Object::Variant {
+                variant_type,
+                payload,
+            } => visitor.visit_enum(EnumAccess {
+                variant: Key::Name(&variant_type.tag),
+                content: Content::Payload(payload),
+            }),
+            o => Err(Error::new(format!(
+                "expected a String, a Hash with one key or a Variant for an enum, got = {}",
+                o.type_name()
+            ))),
+        }
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple tuple_struct map struct
+        identifier ignored_any
+    }
+}
+
+fn visit_seq<'de, I, V>(elements: I, visitor: V) -> Result<V::Value, Error>
+where
+    I: Iterator<Item = &'de Value>,
+    V: de::Visitor<'de>,
+{
+    visitor.visit_seq(SeqAccess {
+        elements: elements.map(|e| e.as_ref()).collect::<Vec<_>>().into_iter(),
+    })
+}
+
+struct SeqAccess<'de> {
+    elements: std::vec::IntoIter<&'de Object>,
+}
+
+impl<'de> de::SeqAccess<'de> for SeqAccess<'de> {
+    type Error = Error;
+
+    fn next_element_seed<T: de::DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>, Error> {
+        match self.elements.next() {
+            Some(element) => seed.deserialize(element).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.elements.len())
+    }
+}
+
+// A hash key is a value; a struct field or variant tag is just a name.
+#[derive(Clone, Copy)]
+enum Key<'de> {
+    Value(&'de Object),
+    Name(&'de str),
+}
+
+impl<'de> Key<'de> {
+    fn deserialize<T: de::DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value, Error> {
+        match self {
+            Key::Value(key) => seed.deserialize(key),
+            Key::Name(name) => seed.deserialize(de::value::BorrowedStrDeserializer::new(name)),
+        }
+    }
+}
+
+struct MapAccess<'de> {
+    entries: std::vec::IntoIter<(Key<'de>, Content<'de>)>,
+    value: Option<Content<'de>>,
+}
+
+impl<'de> de::MapAccess<'de> for MapAccess<'de> {
+    type Error = Error;
+
+    fn next_key_seed<K: de::DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>, Error> {
+        match self.entries.next() {
+            Some((key, value)) => {
+                self.value = Some(value);
+                key.deserialize(seed).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V: de::DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, Error> {
+        let value = self
+            .value
+            .take()
+            .ok_or_else(|| Error::new("next_value_seed called before next_key_seed"))?;
+        match value {
+            Content::Value(value) => seed.deserialize(value),
+            Content::Payload(payload) => seed.deserialize(PayloadDeserializer(payload)),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.entries.len())
+    }
+}
+
+// What follows the tag of an enum: the value of a tagged hash, or a Variant's payload.
+enum Content<'de> {
+    Value(&'de Object),
+    Payload(&'de [Value]),
+}
+
+// A Variant payload of several values reads as a sequence.
+struct PayloadDeserializer<'de>(&'de [Value]);
+
+impl<'de> Deserializer<'de> for PayloadDeserializer<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visit_seq(self.0.iter(), visitor)
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct seq tuple
+        tuple_struct map struct enum identifier ignored_any
+    }
+}
+
+struct EnumAccess<'de> {
+    variant: Key<'de>,
+    content: Content<'de>,
+}
+
+impl<'de> de::EnumAccess<'de> for EnumAccess<'de> {
+    type Error = Error;
+    type Variant = Content<'de>;
+
+    fn variant_seed<V: de::DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, Content<'de>), Error> {
+        Ok((self.variant.deserialize(seed)?, self.content))
+    }
+}
+
+impl<'de> de::VariantAccess<'de> for Content<'de> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<(), Error> {
+        match self {
+            Content::Value(Object::Null) => Ok(()),
+            Content::Payload([]) => Ok(()),
+            Content::Value(o) => Err(Error::new(format!("expected Null for a unit variant, got = {}", o.type_name()))),
+            Content::Payload(_) => Err(Error::new("expected a Variant without payload")),
+        }
+    }
+
+    fn newtype_variant_seed<T: de::DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value, Error> {
+        match self {
+            Content::Value(value) => seed.deserialize(value),
+            Content::Payload(payload) if payload.len() == 1 => seed.deserialize(payload[0].as_ref()),
+            Content::Payload(payload) => Err(Error::new(format!(
+                "expected a Variant with 1 value, got = {}",
+                payload.len()
+            ))),
+        }
+    }
+
+    fn tuple_variant<V: de::Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value, Error> {
+        match self {
+            Content::Value(value) => value.deserialize_seq(visitor),
+            Content::Payload(payload) => visit_seq(payload.iter(), visitor),
+        }
+    }
+
+    fn struct_variant<V: de::Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        match self {
+            Content::Value(value) => value.deserialize_map(visitor),
+            Content::Payload(payload) => visit_seq(payload.iter(), visitor),
+        }
+    }
+}
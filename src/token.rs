@@ -21,6 +21,7 @@ pub enum Token {
 
     Comma,
     Colon,
+    Dot,
     SemiColon,
 
     LParen,
@@ -28,10 +29,16 @@ pub enum Token {
     LBrace,
     RBrace,
     LBracket,
+    SetBrace,
     RBracket,
 
     Eq,
     NotEq,
+    Pipe,
+    FatArrow,
+    NullCoalesce,
+    Question,
+    OptionalDot,
 
     Function,
     Let,
@@ -40,6 +47,18 @@ pub enum Token {
     If,
     Else,
     Return,
+    Null,
+    Try,
+    Catch,
+    Finally,
+    Throw,
+    Struct,
+    Enum,
+    Match,
+    Impl,
+    Import,
+    Export,
+    As,
 
     Str(String),
 }
@@ -59,14 +78,21 @@ impl FromStr for Token {
             "," => Ok(Token::Comma),
             ";" => Ok(Token::SemiColon),
             ":" => Ok(Token::Colon),
+            "." => Ok(Token::Dot),
             "(" => Ok(Token::LParen),
             ")" => Ok(Token::RParen),
             "{" => Ok(Token::LBrace),
             "}" => Ok(Token::RBrace),
             "[" => Ok(Token::LBracket),
+            "#{" => Ok(Token::SetBrace),
             "]" => Ok(Token::RBracket),
             "==" => Ok(Token::Eq),
             "!=" => Ok(Token::NotEq),
+            "|>" => Ok(Token::Pipe),
+            "=>" => Ok(Token::FatArrow),
+            "??" => Ok(Token::NullCoalesce),
+            "?" => Ok(Token::Question),
+            "?." => Ok(Token::OptionalDot),
             "fn" => Ok(Token::Function),
             "let" => Ok(Token::Let),
             "true" => Ok(Token::True),
@@ -74,6 +100,18 @@ impl FromStr for Token {
             "if" => Ok(Token::If),
             "else" => Ok(Token::Else),
             "return" => Ok(Token::Return),
+            "null" => Ok(Token::Null),
+            "try" => Ok(Token::Try),
+            "catch" => Ok(Token::Catch),
+            "finally" => Ok(Token::Finally),
+            "throw" => Ok(Token::Throw),
+            "struct" => Ok(Token::Struct),
+            "enum" => Ok(Token::Enum),
+            "match" => Ok(Token::Match),
+            "impl" => Ok(Token::Impl),
+            "import" => Ok(Token::Import),
+            "export" => Ok(Token::Export),
+            "as" => Ok(Token::As),
             _ => Err(()),
         }
     }
@@ -98,14 +136,21 @@ impl fmt::Display for Token {
             Token::Comma => write!(f, ","),
             Token::SemiColon => write!(f, ";"),
             Token::Colon => write!(f, ":"),
+            Token::Dot => write!(f, "."),
             Token::LParen => write!(f, "("),
             Token::RParen => write!(f, ")"),
             Token::LBrace => write!(f, "{{"),
             Token::RBrace => write!(f, "}}"),
             Token::LBracket => write!(f, "["),
+            Token::SetBrace => write!(f, "#{{"),
             Token::RBracket => write!(f, "]"),
             Token::Eq => write!(f, "=="),
             Token::NotEq => write!(f, "!="),
+            Token::Pipe => write!(f, "|>"),
+            Token::FatArrow => write!(f, "=>"),
+            Token::NullCoalesce => write!(f, "??"),
+            Token::Question => write!(f, "?"),
+            Token::OptionalDot => write!(f, "?."),
             Token::Function => write!(f, "function"),
             Token::Let => write!(f, "let"),
             Token::True => write!(f, "true"),
@@ -113,10 +158,37 @@ impl fmt::Display for Token {
             Token::If => write!(f, "if"),
             Token::Else => write!(f, "else"),
             Token::Return => write!(f, "return"),
+            Token::Null => write!(f, "null"),
+            Token::Try => write!(f, "try"),
+            Token::Catch => write!(f, "catch"),
+            Token::Finally => write!(f, "finally"),
+            Token::Throw => write!(f, "throw"),
+            Token::Struct => write!(f, "struct"),
+            Token::Enum => write!(f, "enum"),
+            Token::Match => write!(f, "match"),
+            Token::Impl => write!(f, "impl"),
+            Token::Import => write!(f, "import"),
+            Token::Export => write!(f, "export"),
+            Token::As => write!(f, "as"),
         }
     }
 }
 
+// A place in the source; line and column count from 1, offset counts characters from 0.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+    pub offset: usize,
+}
+
+// The source range of a token or node, from its first character up to but not including `end`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Span {
+    pub start: Position,
+    pub end: Position,
+}
+
 impl Token {
     pub fn is_same(&self, other: &Token) -> bool {
         use std::mem;
@@ -0,0 +1,231 @@
+extern crate rust_monkey;
+
+use rust_monkey::ast::Node;
+use rust_monkey::ast_json::{from_json, to_json};
+use rust_monkey::environment::Environment;
+use rust_monkey::evaluator::eval;
+use rust_monkey::json::Json;
+use rust_monkey::lexer::Lexer;
+use rust_monkey::parser::Parser;
+
+const PROGRAM: &str = r#"
+struct Point { x, y }
+impl Point { fn sum(self) { self.x + self.y } }
+enum Shape { Circle(r), Square(s) }
+fn area(shape) {
+    match (shape) { Circle(r) => 3 * r * r, Square(s) => s * s, _ => null }
+}
+let p = Point { x: 1, y: -2 };
+let items = [area(Circle(2)), area(Square(3)), p.sum(), p?.x ?? 0];
+let lookup = {"a": #{1, 2}, "b": items[1:3]};
+let safe = try { throw "boom"; } catch (e) { len(e) } finally { null };
+let pick = fn(flag) { if (!flag) { "no" } else { "yes" } };
+[items, lookup["b"], safe, pick(true), pick(false)]
+"#;
+
+fn parse(input: &str) -> Box<Node> {
+    let mut parser = Parser::new(Lexer::new(input.to_owned()));
+    let program = parser.parse_program().unwrap();
+    assert_eq!(parser.errors(), &Vec::<String>::new());
+    program
+}
+
+fn run(program: &Box<Node>) -> String {
+    eval(program, &mut Environment::new()).unwrap().to_string()
+}
+
+#[test]
+fn test_round_trip() {
+    let program = parse(PROGRAM);
+    let json = to_json(&program, true);
+    let text = json.stringify(None);
+    let rebuilt = from_json(&Json::parse(&text).unwrap()).unwrap();
+
+    assert_eq!(format!("{:?}", rebuilt), format!("{:?}", program));
+    assert_eq!(to_json(&rebuilt, true), json);
+    assert_eq!(run(&rebuilt), run(&program));
+    assert_eq!(run(&rebuilt), "[[12, 9, -1, 1], [9, -1], 4, yes, no]");
+}
+
+#[test]
+fn test_node_format() {
+    let program = parse("x + 1");
+    assert_eq!(
+        to_json(&program, false).stringify(None),
+        r#"{"kind":"Program","statements":[{"kind":"Statement","node":{"kind":"ExpressionStatement","token":{"Ident":"x"},"expression":{"kind":"Expression","node":{"kind":"InfixExpression","token":"Plus","left":{"kind":"Expression","node":{"kind":"Identifier","token":{"Ident":"x"},"value":"x"}},"operator":"+","right":{"kind":"Expression","node":{"kind":"IntegerLiteral","token":{"Int":"1"},"value":1}}}}}}]}"#
+    );
+
+    let program = parse("if (x) { 1 }");
+    let json = to_json(&program, false);
+    let statement = &json.get("statements").unwrap();
+    let expression = match statement {
+        Json::Array(statements) => statements[0]
+            .get("node")
+            .unwrap()
+            .get("expression")
+            .unwrap(),
+        _ => unreachable!(),
+    };
+    assert_eq!(
+        expression.get("node").unwrap().get("alternative"),
+        Some(&Json::Null)
+    );
+}
+
+#[test]
+fn test_spans() {
+    let program = parse("let x = 1;\nlet total = x + 20;");
+    let json = to_json(&program, true);
+    let span = |json: &Json| json.get("span").unwrap().stringify(None);
+    let position = |line: i64, column: i64, offset: i64| {
+        format!(
+            r#"{{"line":{},"column":{},"offset":{}}}"#,
+            line, column, offset
+        )
+    };
+
+    let statements = match json.get("statements").unwrap() {
+        Json::Array(statements) => statements.clone(),
+        _ => unreachable!(),
+    };
+    let second = statements[1].get("node").unwrap();
+    assert_eq!(
+        span(second),
+        format!(
+            r#"{{"start":{},"end":{}}}"#,
+            position(2, 1, 11),
+            position(2, 20, 30)
+        )
+    );
+    let name = second.get("name").unwrap();
+    assert_eq!(
+        span(name),
+        format!(
+            r#"{{"start":{},"end":{}}}"#,
+            position(2, 5, 15),
+            position(2, 10, 20)
+        )
+    );
+    let sum = second.get("value").unwrap().get("node").unwrap();
+    assert_eq!(
+        span(sum),
+        format!(
+            r#"{{"start":{},"end":{}}}"#,
+            position(2, 13, 23),
+            position(2, 19, 29)
+        )
+    );
+
+    // The reader keeps the spans.
+    let rebuilt = from_json(&json).unwrap();
+    assert_eq!(format!("{:?}", rebuilt), format!("{:?}", program));
+    assert_eq!(to_json(&rebuilt, true), json);
+
+    // A pipe rebuilds its right side into a call; the parts keep their own spans.
+    let json = to_json(&parse("[1, 2] |> len"), true);
+    let call = match json.get("statements").unwrap() {
+        Json::Array(statements) => statements[0]
+            .get("node")
+            .unwrap()
+            .get("expression")
+            .unwrap()
+            .get("node")
+            .unwrap()
+            .clone(),
+        _ => unreachable!(),
+    };
+    let range = |start: (i64, i64), end: (i64, i64)| {
+        format!(
+            r#"{{"start":{},"end":{}}}"#,
+            position(1, start.0, start.1),
+            position(1, end.0, end.1)
+        )
+    };
+    assert_eq!(span(&call), range((1, 0), (14, 13)));
+    let argument = match call.get("arguments").unwrap() {
+        Json::Array(arguments) => arguments[0].clone(),
+        _ => unreachable!(),
+    };
+    assert_eq!(span(&argument), range((1, 0), (7, 6)));
+    let function = call.get("function").unwrap().get("node").unwrap();
+    assert_eq!(span(function), range((11, 10), (14, 13)));
+}
+
+#[test]
+fn test_from_json_errors() {
+    let error = |input: &str| from_json(&Json::parse(input).unwrap()).unwrap_err();
+
+    assert_eq!(error("[]"), "node must be an object with a string \"kind\"");
+    assert_eq!(error(r#"{"kind": "Loop"}"#), "unknown node kind: Loop");
+    assert_eq!(
+        error(r#"{"kind": "Identifier", "token": {"Ident": "x"}}"#),
+        "missing field \"value\" in Identifier"
+    );
+    assert_eq!(
+        error(r#"{"kind": "IntegerLiteral", "token": {"Int": "1"}, "value": "1"}"#),
+        "field \"value\" in IntegerLiteral must be a number"
+    );
+    assert_eq!(
+        error(r#"{"kind": "Boolean", "token": "Yes", "value": true}"#),
+        "unknown token: Yes"
+    );
+    assert_eq!(
+        error(r#"{"kind": "Program", "statements": {}}"#),
+        "field \"statements\" in Program must be an array"
+    );
+    assert_eq!(
+        error(
+            r#"{"kind": "LetStatement", "token": "Let",
+                "name": {"kind": "IntegerLiteral", "token": {"Int": "1"}, "value": 1},
+                "value": {"kind": "NullLiteral", "token": "Null"}}"#
+        ),
+        "invalid LetStatement: a child has the wrong kind"
+    );
+}
+
+#[test]
+fn test_generated_program() {
+    let string = |s: &str| Json::String(s.to_owned());
+    let node = |kind: &str, mut fields: Vec<(&str, Json)>| {
+        fields.insert(0, ("kind", string(kind)));
+        Json::Object(fields.into_iter().map(|(k, v)| (k.to_owned(), v)).collect())
+    };
+    let token = |tag: &str, value: &str| Json::Object(vec![(tag.to_owned(), string(value))]);
+    let expression = |inner: Json| node("Expression", vec![("node", inner)]);
+
+    let call = expression(node(
+        "CallExpression",
+        vec![
+            ("token", string("LParen")),
+            (
+                "function",
+                expression(node(
+                    "Identifier",
+                    vec![("token", token("Ident", "len")), ("value", string("len"))],
+                )),
+            ),
+            (
+                "arguments",
+                Json::Array(vec![expression(node(
+                    "StringLiteral",
+                    vec![("token", token("Str", "hello")), ("value", string("hello"))],
+                ))]),
+            ),
+        ],
+    ));
+    let statement = node(
+        "Statement",
+        vec![(
+            "node",
+            node(
+                "ExpressionStatement",
+                vec![("token", token("Ident", "len")), ("expression", call)],
+            ),
+        )],
+    );
+    let program = node(
+        "Program",
+        vec![("statements", Json::Array(vec![statement]))],
+    );
+    assert_eq!(run(&from_json(&program).unwrap()), "5");
+}
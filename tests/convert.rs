@@ -0,0 +1,164 @@
+extern crate rust_monkey;
+
+use rust_monkey::convert::{
+    field, from_monkey, into_monkey, ConversionError, FromMonkey, HashBuilder, IntoMonkey,
+};
+use rust_monkey::interpreter::Interpreter;
+use rust_monkey::object::Value;
+
+use std::collections::HashMap;
+
+#[derive(Debug, PartialEq)]
+struct User {
+    name: String,
+    age: u8,
+    email: Option<String>,
+}
+
+impl IntoMonkey for User {
+    fn into_monkey(self) -> Value {
+        HashBuilder::new()
+            .field("name", self.name)
+            .field("age", self.age)
+            .field("email", self.email)
+            .build()
+    }
+}
+
+impl FromMonkey for User {
+    fn from_monkey(value: &Value) -> Result<User, ConversionError> {
+        Ok(User {
+            name: field(value, "name")?,
+            age: field(value, "age")?,
+            email: field(value, "email")?,
+        })
+    }
+}
+
+fn eval(input: &str) -> Value {
+    Interpreter::new().eval_str(input).unwrap()
+}
+
+fn error<T: FromMonkey + std::fmt::Debug>(input: &str) -> String {
+    from_monkey::<T>(&eval(input)).unwrap_err().to_string()
+}
+
+#[test]
+fn test_into_monkey() {
+    assert_eq!(into_monkey(42i64).to_string(), "42");
+    assert_eq!(into_monkey(7u8).to_string(), "7");
+    assert_eq!(into_monkey(true).to_string(), "true");
+    assert_eq!(into_monkey("hi").to_string(), "hi");
+    assert_eq!(into_monkey(String::from("hi")).to_string(), "hi");
+    assert_eq!(into_monkey(()).to_string(), "null");
+    assert_eq!(into_monkey(None::<i32>).to_string(), "null");
+    assert_eq!(into_monkey(Some(1)).to_string(), "1");
+    assert_eq!(into_monkey(vec![1, 2, 3]).to_string(), "[1, 2, 3]");
+    assert_eq!(into_monkey((1, "a", false)).to_string(), "[1, a, false]");
+
+    let mut map = HashMap::new();
+    map.insert("b".to_owned(), vec![2]);
+    map.insert("a".to_owned(), vec![1]);
+    assert_eq!(into_monkey(map).to_string(), "{a: [1], b: [2]}");
+
+    let user = User {
+        name: "ann".to_owned(),
+        age: 30,
+        email: None,
+    };
+    assert_eq!(
+        into_monkey(user).to_string(),
+        "{name: ann, age: 30, email: null}"
+    );
+}
+
+#[test]
+fn test_from_monkey() {
+    assert_eq!(from_monkey::<i64>(&eval("40 + 2")), Ok(42));
+    assert_eq!(from_monkey::<usize>(&eval("3")), Ok(3));
+    assert_eq!(from_monkey::<bool>(&eval("1 < 2")), Ok(true));
+    assert_eq!(from_monkey::<String>(&eval(r#""a" + "b""#)), Ok("ab".to_owned()));
+    assert_eq!(from_monkey::<()>(&eval("null")), Ok(()));
+    assert_eq!(from_monkey::<Option<i32>>(&eval("null")), Ok(None));
+    assert_eq!(from_monkey::<Option<i32>>(&eval("5")), Ok(Some(5)));
+    assert_eq!(from_monkey::<Vec<i32>>(&eval("[1, 2]")), Ok(vec![1, 2]));
+    assert_eq!(
+        from_monkey::<(i32, String)>(&eval(r#"[1, "x"]"#)),
+        Ok((1, "x".to_owned()))
+    );
+
+    let map = from_monkey::<HashMap<String, Vec<bool>>>(&eval(r#"{"a": [true]}"#)).unwrap();
+    assert_eq!(map["a"], vec![true]);
+
+    let user = from_monkey::<User>(&eval(r#"{"name": "bob", "age": 41}"#)).unwrap();
+    assert_eq!(
+        user,
+        User {
+            name: "bob".to_owned(),
+            age: 41,
+            email: None,
+        }
+    );
+
+    let user = from_monkey::<User>(&eval(r#"struct U { name, age } U("cy", 7)"#)).unwrap();
+    assert_eq!(user.name, "cy");
+    assert_eq!(user.age, 7);
+    assert_eq!(user.email, None);
+
+    let value = into_monkey(vec![(1, true), (2, false)]);
+    assert_eq!(from_monkey::<Vec<(u32, bool)>>(&value), Ok(vec![(1, true), (2, false)]));
+}
+
+#[test]
+fn test_conversion_errors() {
+    assert_eq!(error::<i64>(r#""1""#), "expected Integer, got = String");
+    assert_eq!(error::<u8>("300"), "expected Integer in range of u8, got = Integer 300");
+    assert_eq!(error::<u32>("-1"), "expected Integer in range of u32, got = Integer -1");
+    match from_monkey::<u8>(&eval("300")) {
+        Err(e) => assert_eq!((e.expected.as_str(), e.actual.as_str()), ("Integer in range of u8", "Integer")),
+        result => panic!("expected a conversion error, got {:?}", result),
+    }
+    assert_eq!(error::<bool>("null"), "expected Bool, got = Null");
+    assert_eq!(error::<String>("[1]"), "expected String, got = Array");
+    assert_eq!(error::<()>("1"), "expected Null, got = Integer");
+    assert_eq!(error::<Vec<i64>>(r#"[1, "2"]"#), "1: expected Integer, got = String");
+    assert_eq!(
+        error::<HashMap<String, i64>>("{1: 2}"),
+        "expected String, got = Integer"
+    );
+    assert_eq!(
+        error::<HashMap<String, i64>>(r#"{"a": true}"#),
+        "a: expected Integer, got = Bool"
+    );
+    assert_eq!(
+        error::<(i64, i64)>("[1, 2, 3]"),
+        "expected Array of length 2, got = Array of length 3"
+    );
+    assert_eq!(error::<(i64, i64)>("1"), "expected Array of length 2, got = Integer");
+    assert_eq!(error::<User>("[]"), "expected Hash or Struct, got = Array");
+    assert_eq!(error::<User>(r#"{"age": 1}"#), "name: expected String, got = Null");
+    assert_eq!(
+        error::<User>(r#"{"name": "c", "age": 1, "email": 2}"#),
+        "email: expected String, got = Integer"
+    );
+}
+
+#[test]
+fn test_conversion_in_host_functions() {
+    let mut interpreter = Interpreter::new();
+    interpreter.register_fn("oldest", |args: &[Value]| {
+        let users = from_monkey::<Vec<User>>(&args[0])?;
+        let oldest = users.into_iter().max_by_key(|user| user.age);
+        Ok(oldest.map(|user| user.name).into_monkey())
+    });
+    let users = r#"[{"name": "a", "age": 3}, {"name": "b", "age": 9}]"#;
+    assert_eq!(
+        interpreter.eval_str(&format!("oldest({})", users)).unwrap().to_string(),
+        "b"
+    );
+    assert_eq!(interpreter.eval_str("oldest([])").unwrap().to_string(), "null");
+    assert_eq!(
+        interpreter.eval_str("oldest([1])").unwrap_err().to_string(),
+        "0: expected Hash or Struct, got = Integer"
+    );
+}
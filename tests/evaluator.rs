@@ -1,12 +1,18 @@
 extern crate rust_monkey;
 
-use rust_monkey::environment::Environment;
-use rust_monkey::evaluator::eval;
+use rust_monkey::environment::{DuplicateKeyPolicy, Environment};
+use rust_monkey::evaluator::{eval, eval_file};
 use rust_monkey::lexer::Lexer;
-use rust_monkey::object::Object;
+use rust_monkey::native;
+use rust_monkey::object::{Object, Value};
 use rust_monkey::parser::Parser;
 
 use core::borrow::Borrow;
+use std::cell::Cell;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
 use std::rc::Rc;
 
 #[test]
@@ -78,6 +84,904 @@ fn test_evaluator() {
     assert_eq_int(evaluate(r#"{"one":1, "two":2}["one"]"#), 1);
 }
 
+#[test]
+fn test_function_declaration() {
+    assert_eq_int(evaluate("fn add(a, b) { a + b } add(1, 2)"), 3);
+    assert_eq_int(evaluate("let x = add(1, 2); fn add(a, b) { a + b } x"), 3);
+    assert_eq_int(
+        evaluate("fn fact(n) { if (n < 2) { 1 } else { n * fact(n - 1) } } fact(5)"),
+        120,
+    );
+    assert_eq_bool(
+        evaluate(
+            "let r = is_even(10);
+             fn is_even(n) { if (n == 0) { true } else { is_odd(n - 1) } }
+             fn is_odd(n) { if (n == 0) { false } else { is_even(n - 1) } }
+             r",
+        ),
+        true,
+    );
+    assert_eq_int(
+        evaluate("fn outer() { return inner(); fn inner() { 7 } } outer()"),
+        7,
+    );
+    // Functions see the scope they were defined in as it is when they are called.
+    assert_eq_int(evaluate("let x = 1; let f = fn() { x }; let x = 2; f()"), 2);
+    assert_eq_int(evaluate("fn make() { let f = fn() { n }; let n = 3; f } make()()"), 3);
+    assert_eq_display(evaluate("fn add(a, b) { a + b }"), "fn add(a, b)");
+    assert_eq_display(evaluate("fn(x) { x }"), "fn(x)");
+    assert_eq_null(evaluate("fn nothing() {} nothing()"));
+    assert_eq_error(evaluate("let x = 5; x(1)"), "not a function: Integer");
+    assert_eq_error(evaluate(r#""abc"()"#), "not a function: String");
+}
+
+#[test]
+fn test_member_access() {
+    assert_eq_int(evaluate(r#"{"a": 1}.a"#), 1);
+    assert_eq_int(evaluate(r#"let h = {"inner": {"b": 2}}; h.inner.b"#), 2);
+    assert_eq_null(evaluate(r#"{"a": 1}.b"#));
+    assert_eq_error(evaluate("5.a"), "member access not supported: Integer(5)");
+
+    assert_eq_int(evaluate("[1, 2, 3].len()"), 3);
+    assert_eq_int(evaluate(r#""abc".len()"#), 3);
+    assert_eq_int(evaluate("[1, 2, 3].push(4).len()"), 4);
+    assert_eq_int(evaluate("let a = [1, 2]; a.push(3).last()"), 3);
+    assert_eq_int(evaluate(r#"{"double": fn(x) { x * 2 }}.double(3)"#), 6);
+    assert_eq_error(evaluate("[1].nope()"), "unknown method: nope");
+}
+
+#[test]
+fn test_pipe() {
+    assert_eq_int(evaluate("[1, 2, 3] |> len()"), 3);
+    assert_eq_int(evaluate("[1, 2] |> push(3) |> push(4) |> len()"), 4);
+    assert_eq_int(evaluate("fn add(a, b) { a + b } 1 |> add(2)"), 3);
+    assert_eq_int(evaluate("let double = fn(x) { x * 2 }; 5 |> double"), 10);
+    assert_eq_int(evaluate("fn add(a, b) { a + b } 1 + 2 |> add(3) |> add(4)"), 10);
+    assert_eq_int(evaluate("fn sub(a, b) { a - b } 10 |> sub(3)"), 7);
+}
+
+#[test]
+fn test_null() {
+    assert_eq_null(evaluate("null"));
+    assert_eq_bool(evaluate("null == null"), true);
+    assert_eq_bool(evaluate("1 == null"), false);
+    assert_eq_bool(evaluate("null != 1"), true);
+    assert_eq_bool(evaluate("!null"), true);
+    assert_eq_null(evaluate(r#"{"a": 1}["b"]"#));
+
+    assert_eq_int(evaluate("null ?? 5"), 5);
+    assert_eq_int(evaluate("3 ?? 5"), 3);
+    assert_eq_bool(evaluate("false ?? true"), false);
+    assert_eq_int(evaluate(r#"let config = {"port": 80}; config.host ?? config.port"#), 80);
+    assert_eq_int(evaluate("null ?? null ?? 7"), 7);
+    assert_eq_int(evaluate("1 ?? undefined_name"), 1);
+
+    assert_eq_null(evaluate("let a = null; a?.b"));
+    assert_eq_null(evaluate("let a = null; a?.[0]"));
+    assert_eq_null(evaluate("let a = null; a?.len()"));
+    assert_eq_int(evaluate(r#"{"a": {"b": 2}}?.a?.b"#), 2);
+    assert_eq_null(evaluate(r#"{"a": 1}?.x?.y"#));
+    assert_eq_int(evaluate("[1, 2]?.[1]"), 2);
+    assert_eq_int(evaluate(r#"{"db": null}.db?.port ?? 5432"#), 5432);
+
+    // A null found by an optional link skips the rest of the chain.
+    assert_eq_null(evaluate("let n = null; n?.b.c"));
+    assert_eq_null(evaluate("let n = null; n?.b.c.d"));
+    assert_eq_null(evaluate("let n = null; n?.b[0].c"));
+    assert_eq_null(evaluate("let n = null; n?.items[1:2]"));
+    assert_eq_null(evaluate("let n = null; n?.b.len()"));
+    assert_eq_null(evaluate("let n = null; n?.[0].c(undefined_name)"));
+    assert_eq_int(evaluate("let n = null; n?.b.c ?? 3"), 3);
+    assert_eq_error(evaluate(r#"let h = {"b": null}; h?.b.c"#), "member access not supported: Null");
+}
+
+#[test]
+fn test_try_catch() {
+    assert_eq_int(evaluate("try { 1 } catch (e) { 2 }"), 1);
+    assert_eq_int(evaluate("try { throw 1; 5 } catch (e) { 2 }"), 2);
+    assert_eq_string(evaluate(r#"try { throw "boom"; } catch (e) { e.message }"#), "boom");
+    assert_eq_string(evaluate(r#"try { throw "boom"; } catch (e) { e.kind }"#), "Error");
+    assert_eq_int(evaluate("try { throw 42; } catch (e) { e.value }"), 42);
+    assert_eq_string(
+        evaluate(r#"try { throw {"kind": "Custom"}; } catch (e) { e.kind }"#),
+        "Custom",
+    );
+
+    assert_eq_string(evaluate("try { 1 + true } catch (e) { e.kind }"), "TypeError");
+    assert_eq_string(
+        evaluate("try { 1 + true } catch (e) { e.message }"),
+        "type mismatch: Integer(1), \"+\", Bool(true)",
+    );
+    assert_eq_string(evaluate("try { nope } catch (e) { e.kind }"), "NameError");
+    assert_eq_string(evaluate("try { len(1) } catch (e) { e.kind }"), "ArgumentError");
+    assert_eq_string(evaluate("try { len(1, 2) } catch (e) { e.message }"), "wrong number of arguments, got = 2, want = 1");
+    assert_eq_string(evaluate("try { push(len(1), 2) } catch (e) { e.kind }"), "ArgumentError");
+
+    assert_eq_display(
+        evaluate(
+            "fn inner() { throw \"x\"; }
+             fn outer() { inner() }
+             try { outer() } catch (e) { e.stack }",
+        ),
+        "[inner, outer]",
+    );
+    assert_eq_display(
+        evaluate("let f = fn() { 1 + true }; try { f() } catch (e) { e.stack }"),
+        "[<anonymous>]",
+    );
+    assert_eq_display(evaluate("try { throw 1; } catch (e) { e.stack }"), "[]");
+
+    assert_eq_int(evaluate("let x = try { 1 } finally { 2 }; x"), 1);
+    assert_eq_int(evaluate("let x = try { throw 1; } catch (e) { 2 } finally { 3 }; x"), 2);
+    assert_eq_int(evaluate("fn f() { try { return 1; } finally { 2 } } f()"), 1);
+    assert_eq_int(evaluate("fn f() { try { 1 } finally { return 2; } } f()"), 2);
+    assert_eq_display(evaluate("try { throw 1; } finally { 2 }"), "ERROR: 1");
+    assert_eq_int(
+        evaluate("try { try { throw 1; } catch (e) { throw e.value + 1; } } catch (e) { e.value }"),
+        2,
+    );
+    assert_eq_int(evaluate("try { throw 1; } catch (e) { 2 }; 3"), 3);
+    assert_eq_display(evaluate("throw 5;"), "ERROR: 5");
+    assert_eq_error(evaluate("try { 1 } catch (e) { 2 }; 1 + true"), "type mismatch: Integer(1), \"+\", Bool(true)");
+}
+
+#[test]
+fn test_result() {
+    assert_eq_display(evaluate("ok(1)"), "ok(1)");
+    assert_eq_display(evaluate(r#"err("bad")"#), "err(bad)");
+    assert_eq_bool(evaluate("is_ok(ok(1))"), true);
+    assert_eq_bool(evaluate("is_ok(err(1))"), false);
+    assert_eq_bool(evaluate("err(1).is_err()"), true);
+    assert_eq_int(evaluate("unwrap(ok(5))"), 5);
+    assert_eq_error(evaluate("unwrap(err(5))"), "called unwrap on err: 5");
+    assert_eq_int(evaluate("unwrap_or(ok(5), 0)"), 5);
+    assert_eq_int(evaluate("err(5).unwrap_or(0)"), 0);
+    assert_eq_bool(evaluate("ok(1) == ok(1)"), true);
+    assert_eq_bool(evaluate("ok(1) == err(1)"), false);
+
+    let checked_div = "fn div(a, b) { if (b == 0) { err(\"division by zero\") } else { ok(a / b) } }";
+    assert_eq_int(
+        evaluate(&format!("{} fn f() {{ let x = div(10, 2)?; ok(x + 1) }} unwrap(f())", checked_div)),
+        6,
+    );
+    assert_eq_display(
+        evaluate(&format!("{} fn f() {{ let x = div(10, 0)?; ok(x + 1) }} f()", checked_div)),
+        "err(division by zero)",
+    );
+    assert_eq_display(
+        evaluate(&format!("{} fn f() {{ div(1, 0)? + div(1, 1)? }} f()", checked_div)),
+        "err(division by zero)",
+    );
+    assert_eq_display(
+        evaluate(&format!("{} fn f() {{ [div(4, 2)?, div(1, 0)?] }} f()", checked_div)),
+        "err(division by zero)",
+    );
+    assert_eq_int(evaluate("fn f() { err(1)? } let r = f(); 2"), 2);
+    assert_eq_int(evaluate("fn f() { let inner = ok(ok(3))?; inner? } f()"), 3);
+    assert_eq_error(evaluate("5?"), "? operator not supported: Integer(5)");
+
+    // `?.` after a result propagates like `?` and then reads the member.
+    let parse = r#"fn parse(s) { if (len(s) == 0) { err("empty") } else { ok({"value": len(s)}) } }"#;
+    assert_eq_display(
+        evaluate(&format!("{} fn f(s) {{ let v = parse(s)?.value; ok(v * 10) }} f(\"abc\")", parse)),
+        "ok(30)",
+    );
+    assert_eq_display(
+        evaluate(&format!("{} fn f(s) {{ let v = parse(s)?.value; ok(v * 10) }} f(\"\")", parse)),
+        "err(empty)",
+    );
+    assert_eq_int(evaluate(r#"fn f() { ok({"x": 1}) } fn g() { f()?.x } g()"#), 1);
+    assert_eq_display(evaluate(r#"fn f() { err("bad") } fn g() { f()?.x + 1 } g()"#), "err(bad)");
+    assert_eq_int(evaluate("fn f() { ok([1, 2]) } fn g() { f()?.len() } g()"), 2);
+}
+
+#[test]
+fn test_struct() {
+    let point = "struct Point { x, y }";
+    assert_eq_display(evaluate(point), "struct Point");
+    assert_eq_int(evaluate(&format!("{} Point(1, 2).x", point)), 1);
+    assert_eq_int(evaluate(&format!("{} let p = Point {{ y: 2, x: 1 }}; p.y", point)), 2);
+    assert_eq_display(evaluate(&format!("{} Point(1, 2)", point)), "Point { x: 1, y: 2 }");
+    assert_eq_display(evaluate(&format!("{} Point {{ y: 2, x: 1 }}", point)), "Point { x: 1, y: 2 }");
+    assert_eq_display(evaluate("struct Unit {} Unit()"), "Unit {}");
+
+    assert_eq_bool(evaluate(&format!("{} Point(1, 2) == Point {{ x: 1, y: 2 }}", point)), true);
+    assert_eq_bool(evaluate(&format!("{} Point(1, 2) == Point(2, 1)", point)), false);
+    assert_eq_bool(evaluate(&format!("{} Point(1, 2) != Point(2, 1)", point)), true);
+    assert_eq_bool(
+        evaluate(&format!("{} struct Pair {{ x, y }} Point(1, 2) == Pair(1, 2)", point)),
+        false,
+    );
+
+    assert_eq_error(evaluate(&format!("{} Point(1, 2).z", point)), "unknown field: z for Point");
+    assert_eq_error(evaluate(&format!("{} Point {{ x: 1, z: 2 }}", point)), "unknown field: z for Point");
+    assert_eq_error(evaluate(&format!("{} Point {{ x: 1 }}", point)), "missing field: y for Point");
+    assert_eq_error(evaluate(&format!("{} Point {{ x: 1, x: 2 }}", point)), "duplicate field: x");
+    assert_eq_error(evaluate(&format!("{} Point(1)", point)), "wrong number of arguments, got = 1, want = 2");
+    assert_eq_error(evaluate("struct Bad { a, a }"), "duplicate field: a");
+
+    assert_eq_int(
+        evaluate("struct Counter { step } let c = Counter(fn(n) { n + 1 }); c.step(1)"),
+        2,
+    );
+    assert_eq_int(evaluate(&format!("{} fn norm(p) {{ p.x * p.x + p.y * p.y }} norm(Point(3, 4))", point)), 25);
+}
+
+#[test]
+fn test_enum() {
+    let shape = "enum Shape { Circle(r), Rect(w, h), Empty }
+                 fn area(s) {
+                     match (s) {
+                         Circle(r) => 3 * r * r,
+                         Rect(w, h) => w * h,
+                         Empty => 0,
+                     }
+                 }";
+    assert_eq_int(evaluate(&format!("{} area(Circle(2))", shape)), 12);
+    assert_eq_int(evaluate(&format!("{} area(Rect(2, 5))", shape)), 10);
+    assert_eq_int(evaluate(&format!("{} area(Empty)", shape)), 0);
+    assert_eq_int(evaluate(&format!("{} area(Shape.Rect(3, 3))", shape)), 9);
+    assert_eq_int(evaluate(&format!("{} Rect(3, 4).h", shape)), 4);
+
+    assert_eq_display(evaluate(&format!("{} Rect(1, 2)", shape)), "Rect(1, 2)");
+    assert_eq_display(evaluate(&format!("{} Empty", shape)), "Empty");
+    assert_eq_display(evaluate(&format!("{} Shape", shape)), "enum Shape");
+    assert_eq_display(evaluate(&format!("{} Shape.Circle", shape)), "variant Shape.Circle");
+
+    assert_eq_bool(evaluate(&format!("{} Circle(1) == Circle(1)", shape)), true);
+    assert_eq_bool(evaluate(&format!("{} Circle(1) == Circle(2)", shape)), false);
+    assert_eq_bool(evaluate(&format!("{} Empty == Shape.Empty", shape)), true);
+    assert_eq_int(evaluate(&format!(r#"{} let h = {{Circle(1): 7}}; h[Circle(1)]"#, shape)), 7);
+    assert_eq_null(evaluate(&format!(r#"{} let h = {{Circle(1): 7}}; h[Circle(2)]"#, shape)));
+
+    assert_eq_error(evaluate(&format!("{} Circle(1, 2)", shape)), "wrong number of arguments, got = 2, want = 1");
+    assert_eq_error(evaluate(&format!("{} Shape.Square", shape)), "unknown variant: Square for Shape");
+    assert_eq_error(evaluate("enum E { A, A }"), "duplicate variant: A");
+}
+
+#[test]
+fn test_match() {
+    assert_eq_int(evaluate("match (2) { 1 => 10, 2 => 20, _ => 30 }"), 20);
+    assert_eq_int(evaluate("match (5) { 1 => 10, _ => 30 }"), 30);
+    assert_eq_int(evaluate("match (5) { n => n + 1 }"), 6);
+    assert_eq_string(evaluate(r#"match ("a") { "a" => "yes", _ => "no" }"#), "yes");
+    assert_eq_int(evaluate("match (null) { null => 1, _ => 2 }"), 1);
+    assert_eq_int(evaluate("match (true) { false => 1, true => { let x = 2; x * 2 } }"), 4);
+
+    // An arm result in braces is a hash literal when it starts with `key:`, else a block.
+    let opt = "enum Opt { Some(v), None }";
+    assert_eq_display(
+        evaluate(&format!(r#"{} match (Some(1)) {{ Some(x) => {{"a": x}}, None => {{}} }}"#, opt)),
+        "{a: 1}",
+    );
+    assert_eq_int(evaluate(&format!(r#"{} match (Some(2)) {{ Some(x) => {{"a": x}}.a, _ => 0 }}"#, opt)), 2);
+    assert_eq_display(evaluate(r#"match (1) { 1 => {[1, 2][0]: "one", "b": {"c": 3}} }"#), "{1: one, b: {c: 3}}");
+    assert_eq_null(evaluate(&format!("{} match (None) {{ Some(x) => x, None => {{}} }}", opt)));
+    assert_eq_display(evaluate(r#"match (1) { 1 => { let h = {"a": 1}; h } }"#), "{a: 1}");
+    assert_eq_int(evaluate("match (1) { 1 => { if (true) { 5 } else { 6 } } }"), 5);
+    assert_eq_int(evaluate("let a = [1, 2, 3]; match (1) { 1 => { len(a[1:]) } }"), 2);
+    assert_eq_int(
+        evaluate("enum Tree { Leaf(v), Node(l, r) }
+                  fn sum(t) { match (t) { Leaf(v) => v, Node(l, r) => sum(l) + sum(r) } }
+                  sum(Node(Leaf(1), Node(Leaf(2), Leaf(3))))"),
+        6,
+    );
+    assert_eq_int(
+        evaluate("enum Opt { Some(v), None } match (Some(Some(4))) { Some(Some(x)) => x, _ => 0 }"),
+        4,
+    );
+    assert_eq_int(evaluate("enum Opt { Some(v), None } match (Some(9)) { Some(1) => 1, Some => 2 }"), 2);
+    assert_eq_int(evaluate("struct P { x, y } match (P(1, 2)) { P(a, b) => a + b }"), 3);
+    assert_eq_error(evaluate("match (3) { 1 => 1 }"), "no match arm for: 3");
+}
+
+#[test]
+fn test_operator_overloading() {
+    let vector = "struct Vector { x, y }
+                impl Vector {
+                    fn __add__(a, b) { Vector(a.x + b.x, a.y + b.y) }
+                    fn __sub__(a, b) { Vector(a.x - b.x, a.y - b.y) }
+                    fn __mul__(a, k) { Vector(a.x * k, a.y * k) }
+                    fn __neg__(a) { Vector(-a.x, -a.y) }
+                    fn __eq__(a, b) { a.x == b.x }
+                    fn __lt__(a, b) { a.x < b.x }
+                    fn __index__(a, i) { if (i == 0) { a.x } else { a.y } }
+                    fn __str__(a) { \"<vec>\" }
+                    fn sum(a) { a.x + a.y }
+                }";
+    let eval_vector = |input: &str| evaluate(&format!("{} {}", vector, input));
+    assert_eq_display(eval_vector("Vector(1, 2) + Vector(3, 4)"), "Vector { x: 4, y: 6 }");
+    assert_eq_display(eval_vector("Vector(5, 5) - Vector(3, 4)"), "Vector { x: 2, y: 1 }");
+    assert_eq_display(eval_vector("Vector(1, 2) * 3"), "Vector { x: 3, y: 6 }");
+    assert_eq_display(eval_vector("-Vector(1, 2)"), "Vector { x: -1, y: -2 }");
+    assert_eq_bool(eval_vector("Vector(1, 2) == Vector(1, 3)"), true);
+    assert_eq_bool(eval_vector("Vector(1, 2) != Vector(1, 3)"), false);
+    assert_eq_bool(eval_vector("Vector(1, 2) < Vector(2, 0)"), true);
+    assert_eq_bool(eval_vector("Vector(1, 2) > Vector(2, 0)"), false);
+    assert_eq_int(eval_vector("Vector(1, 2)[1]"), 2);
+    assert_eq_int(eval_vector("Vector(1, 2).sum()"), 3);
+    assert_eq_null(eval_vector("puts(Vector(1, 2))"));
+
+    assert_eq_int(
+        evaluate(r#"let money = {"__add__": fn(a, b) { b * 10 }}; money + 2"#),
+        20,
+    );
+    assert_eq_bool(evaluate("struct P { x } P(1) == P(1)"), true);
+    assert_eq_bool(evaluate("struct P { x } P(1) != P(2)"), true);
+    assert!(evaluate("struct P { x } P(1) + P(2)").unwrap().is_error());
+    assert_eq_error(
+        evaluate("struct P { x } impl P { fn __str__(p) { 1 } } puts(P(1))"),
+        "__str__ must return String, got = Integer(1)",
+    );
+    assert_eq_error(evaluate("impl len { }"), "impl target must be a struct, got = builtin len");
+}
+
+#[test]
+fn test_import() {
+    let dir = module_dir("import");
+    write_module(&dir, "lib/math.monkey", r#"
+        import "constants.monkey" as constants;
+        export let square = fn(x) { x * x };
+        export fn add(a, b) { a + b }
+        export let two = constants.two;
+        let hidden = 1;
+    "#);
+    write_module(&dir, "lib/constants.monkey", "export let two = 2;");
+    write_module(&dir, "main.monkey", r#"
+        import "lib/math.monkey" as math;
+        import "lib/constants.monkey" as constants;
+        math.add(math.square(3), constants.two)
+    "#);
+    assert_eq_int(evaluate_file(&dir.join("main.monkey")), 11);
+
+    let math = dir.join("lib/math.monkey").canonicalize().unwrap();
+    write_module(&dir, "hidden.monkey", r#"import "lib/math.monkey" as m; m.hidden"#);
+    assert_eq_error(
+        evaluate_file(&dir.join("hidden.monkey")),
+        &format!("module {} has no export 'hidden'", math.display()),
+    );
+    write_module(&dir, "typo.monkey", r#"import "lib/math.monkey" as m; m.ad(1, 2)"#);
+    assert_eq_error(
+        evaluate_file(&dir.join("typo.monkey")),
+        &format!("module {} has no export 'ad'", math.display()),
+    );
+    write_module(&dir, "display.monkey", r#"import "lib/math.monkey" as m; m"#);
+    assert_eq_display(
+        evaluate_file(&dir.join("display.monkey")),
+        &format!("module {}", math.display()),
+    );
+
+    write_module(&dir, "a.monkey", r#"import "b.monkey" as b; export let a = 1;"#);
+    write_module(&dir, "b.monkey", r#"import "a.monkey" as a; export let b = 2;"#);
+    let a = dir.join("a.monkey").canonicalize().unwrap();
+    let b = dir.join("b.monkey").canonicalize().unwrap();
+    assert_eq_error(
+        evaluate_file(&a),
+        &format!("import cycle: {} -> {} -> {}", a.display(), b.display(), a.display()),
+    );
+
+    write_module(&dir, "missing.monkey", r#"import "nope.monkey" as nope;"#);
+    let result = evaluate_file(&dir.join("missing.monkey")).unwrap().to_string();
+    assert!(result.starts_with("ERROR: cannot import"), "{}", result);
+
+    write_module(&dir, "failing.monkey", r#"export let x = unwrap(err(5));"#);
+    write_module(&dir, "uses_failing.monkey", r#"import "failing.monkey" as f; 1"#);
+    assert_eq_error(
+        evaluate_file(&dir.join("uses_failing.monkey")),
+        "called unwrap on err: 5",
+    );
+}
+
+#[test]
+fn test_prelude() {
+    assert_eq_display(evaluate_with_prelude("map([1, 2, 3], fn(x) { x * 2 })"), "[2, 4, 6]");
+    assert_eq_display(evaluate_with_prelude("map([], fn(x) { x * 2 })"), "[]");
+    assert_eq_int(evaluate_with_prelude("reduce([1, 2, 3, 4], 0, fn(acc, x) { acc + x })"), 10);
+    assert_eq_int(evaluate_with_prelude("reduce([], 7, fn(acc, x) { acc + x })"), 7);
+    assert_eq_display(
+        evaluate_with_prelude("filter([1, 2, 3, 4], fn(x) { x > 2 })"),
+        "[3, 4]",
+    );
+    assert_eq_display(evaluate_with_prelude("range(4)"), "[0, 1, 2, 3]");
+    assert_eq_display(evaluate_with_prelude("range(2, 5)"), "[2, 3, 4]");
+    assert_eq_display(evaluate_with_prelude("range(5, 0, -2)"), "[5, 3, 1]");
+    assert_eq_display(evaluate_with_prelude("range(3, 1)"), "[]");
+    assert_eq_error(evaluate_with_prelude("range(0, 3, 0)"), "range step must not be 0");
+    assert_eq_display(
+        evaluate("range(9223372036854775806, 9223372036854775807, 2)"),
+        "[9223372036854775806]",
+    );
+    assert_eq_display(
+        evaluate("range(-9223372036854775806, -9223372036854775807 - 1, -5)"),
+        "[-9223372036854775806]",
+    );
+    assert_eq_bool(evaluate_with_prelude("contains([1, 2, 3], 2)"), true);
+    assert_eq_bool(evaluate_with_prelude(r#"contains([1, "a"], "b")"#), false);
+    assert_eq_int(
+        evaluate_with_prelude("range(10) |> filter(fn(x) { x > 6 }) |> map(fn(x) { x * x }) |> reduce(0, fn(a, b) { a + b })"),
+        194,
+    );
+    assert_eq_int(evaluate_with_prelude("let map = fn(a, f) { 1 }; map([2], len)"), 1);
+    assert_eq_int(evaluate_with_prelude("len(map(range(20000), fn(x) { x }))"), 20000);
+    assert_eq_display(evaluate_with_prelude("map(#{1, 2}, fn(x) { x * 2 })"), "[2, 4]");
+    assert_eq_display(evaluate_with_prelude("map"), "builtin map");
+    assert_eq_display(evaluate_with_prelude("filter"), "builtin filter");
+    assert_eq_display(evaluate_with_prelude("reduce"), "builtin reduce");
+}
+
+#[test]
+fn test_builtin_signatures() {
+    assert_eq_null(evaluate("first([])"));
+    assert_eq_null(evaluate("last([])"));
+    assert_eq_null(evaluate("rest([])"));
+    assert_eq_int(evaluate("first([1, 2])"), 1);
+    assert_eq_int(evaluate("last([1, 2])"), 2);
+    assert_eq_display(evaluate("rest([1])"), "[]");
+
+    assert_eq_error(evaluate("first([], [])"), "wrong number of arguments, got = 2, want = 1");
+    assert_eq_error(evaluate("push([])"), "wrong number of arguments, got = 1, want = 2");
+    assert_eq_error(evaluate("range()"), "wrong number of arguments, got = 0, want = 1..3");
+    assert_eq_error(evaluate("substr(\"a\", 1, 2, 3)"), "wrong number of arguments, got = 4, want = 2..3");
+    assert_eq_error(evaluate("first(1)"), "argument to first must be Array, got = Integer(1)");
+    assert_eq_error(evaluate("last(1)"), "argument to last must be Array, got = Integer(1)");
+    assert_eq_error(evaluate("rest(1)"), "argument to rest must be Array, got = Integer(1)");
+    assert_eq_error(evaluate("push(1, 2)"), "argument to push must be Array, got = Integer(1)");
+    assert_eq_error(evaluate("len(1)"), "argument to len must be String, Array, Hash or Set, got = Integer(1)");
+    assert_eq_error(evaluate("range(1, \"a\")"), "argument to range must be Integer, got = StringValue(\"a\")");
+    assert_eq_error(evaluate("is_ok(1)"), "argument to is_ok must be Result, got = Integer(1)");
+    assert_eq_error(evaluate("[1].join(2)"), "argument to join must be String, got = Integer(2)");
+    assert_eq_null(evaluate("puts()"));
+    assert_eq_null(evaluate("puts(1, \"a\", [2])"));
+    assert_eq_string(
+        evaluate("try { first(1) } catch (e) { e.kind }"),
+        "ArgumentError",
+    );
+}
+
+#[test]
+fn test_higher_order_builtins() {
+    assert_eq_display(evaluate("map([1, 2, 3], fn(x) { x * 2 })"), "[2, 4, 6]");
+    assert_eq_display(evaluate("[1, 2, 3].map(fn(x) { x + 1 })"), "[2, 3, 4]");
+    assert_eq_display(evaluate("map([], fn(x) { x * 2 })"), "[]");
+    assert_eq_display(evaluate("map([[1], [1, 2]], len)"), "[1, 2]");
+    assert_eq_int(evaluate("reduce([1, 2, 3, 4], 0, fn(acc, x) { acc + x })"), 10);
+    assert_eq_int(evaluate("reduce([], 7, fn(acc, x) { acc + x })"), 7);
+    assert_eq_display(evaluate("filter([1, 2, 3, 4], fn(x) { x > 2 })"), "[3, 4]");
+
+    assert_eq_display(evaluate("sort([3, 1, 2])"), "[1, 2, 3]");
+    assert_eq_display(evaluate(r#"sort(["b", "c", "a"])"#), "[a, b, c]");
+    assert_eq_error(evaluate(r#"sort([1, "a"])"#), "cannot compare a with 1 in sort");
+    assert_eq_display(evaluate("sort_by([3, 1, 2], fn(a, b) { b - a })"), "[3, 2, 1]");
+    assert_eq_display(
+        evaluate("sort_by([[2, 1], [1, 2], [2, 3], [1, 4]], fn(a, b) { a[0] - b[0] }) |> map(fn(p) { p[1] })"),
+        "[2, 4, 1, 3]",
+    );
+    assert_eq_error(
+        evaluate("sort_by([1, 2], fn(a, b) { true })"),
+        "comparator must return Integer, got = Bool(true)",
+    );
+
+    assert_eq_display(evaluate(r#"zip([1, 2, 3], ["a", "b"])"#), "[[1, a], [2, b]]");
+    assert_eq_display(evaluate(r#"enumerate(["a", "b"])"#), "[[0, a], [1, b]]");
+    assert_eq_bool(evaluate("any([1, 2, 3], fn(x) { x > 2 })"), true);
+    assert_eq_bool(evaluate("any([], fn(x) { true })"), false);
+    assert_eq_bool(evaluate("all([1, 2, 3], fn(x) { x > 0 })"), true);
+    assert_eq_bool(evaluate("all([1, 2, 3], fn(x) { x > 1 })"), false);
+    assert_eq_int(evaluate("find([1, 2, 3], fn(x) { x > 1 })"), 2);
+    assert_eq_null(evaluate("find([1, 2, 3], fn(x) { x > 5 })"));
+    assert_eq_display(evaluate("flat_map([1, 2], fn(x) { [x, x * 10] })"), "[1, 10, 2, 20]");
+    assert_eq_error(
+        evaluate("flat_map([1], fn(x) { x })"),
+        "flat_map function must return Array, got = Integer(1)",
+    );
+
+    assert_eq_error(evaluate("map([1], fn(x) { unwrap(err(x)) })"), "called unwrap on err: 1");
+    assert_eq_int(
+        evaluate("try { map([1, 2], fn(x) { throw x * 5; }) } catch (e) { e.value }"),
+        5,
+    );
+    assert_eq_error(evaluate("map(1, len)"), "argument to map must be Array or Set, got = Integer(1)");
+    assert_eq_error(evaluate("map([1], 2)"), "argument to map must be Function, got = Integer(2)");
+}
+
+#[test]
+fn test_string_builtins() {
+    assert_eq_int(evaluate(r#"len("héllo")"#), 5);
+    assert_eq_display(evaluate(r#"split("a,b,,c", ",")"#), "[a, b, , c]");
+    assert_eq_display(evaluate(r#"split("añb", "")"#), "[a, ñ, b]");
+    assert_eq_string(evaluate(r#"join(["a", 1, true], "-")"#), "a-1-true");
+    assert_eq_string(evaluate(r#"join([], ", ")"#), "");
+    assert_eq_string(evaluate(r#"trim("  hi 
+")"#), "hi");
+    assert_eq_string(evaluate(r#"upper("straße")"#), "STRASSE");
+    assert_eq_string(evaluate(r#"lower("ÀB")"#), "àb");
+    assert_eq_string(evaluate(r#"replace("a-b-c", "-", "+")"#), "a+b+c");
+    assert_eq_bool(evaluate(r#"contains("hello", "ell")"#), true);
+    assert_eq_bool(evaluate(r#"contains("hello", "xyz")"#), false);
+    assert_eq_bool(evaluate(r#"starts_with("hello", "he")"#), true);
+    assert_eq_bool(evaluate(r#""hello".ends_with("lo")"#), true);
+    assert_eq_int(evaluate(r#"index_of("héllo", "l")"#), 2);
+    assert_eq_int(evaluate(r#"index_of("hello", "z")"#), -1);
+    assert_eq_int(evaluate("index_of([1, 2, 3], 3)"), 2);
+    assert_eq_string(evaluate(r#"substr("héllo", 1, 3)"#), "éll");
+    assert_eq_string(evaluate(r#"substr("héllo", 3)"#), "lo");
+    assert_eq_string(evaluate(r#"substr("abc", 5)"#), "");
+    assert_eq_string(evaluate(r#"repeat("ab", 3)"#), "ababab");
+    assert_eq_error(evaluate(r#"repeat("ab", -1)"#), "repeat count must not be negative, got = -1");
+    assert_eq_error(
+        evaluate(r#"repeat("ab", 4611686018427387904)"#),
+        "repeat result too long, limit = 1073741824 bytes",
+    );
+    assert_eq_error(
+        evaluate(r#"repeat("ab", 536870913)"#),
+        "repeat result too long, limit = 1073741824 bytes",
+    );
+    assert_eq_string(evaluate(r#"repeat("", 4611686018427387904)"#), "");
+    assert_eq_display(evaluate(r#"chars("日本")"#), "[日, 本]");
+    assert_eq_string(evaluate("to_string(42)"), "42");
+    assert_eq_string(evaluate("to_string([1, 2])"), "[1, 2]");
+    assert_eq_string(
+        evaluate(r#"struct P { x } impl P { fn __str__(p) { "P!" } } to_string(P(1))"#),
+        "P!",
+    );
+    assert_eq_int(evaluate(r#"parse_int("-42")"#), -42);
+    assert_eq_error(evaluate(r#"parse_int("4x")"#), "could not parse as Integer: \"4x\"");
+    assert_eq_string(evaluate(r#"pad_left("7", 3, "0")"#), "007");
+    assert_eq_string(evaluate(r#"pad_right("ñ", 3)"#), "ñ  ");
+    assert_eq_string(evaluate(r#"pad_left("long", 2)"#), "long");
+    assert_eq_error(
+        evaluate(r#"pad_left("a", 3, "ab")"#),
+        "pad_left fill must be a single character, got = \"ab\"",
+    );
+    assert_eq_error(
+        evaluate(r#"pad_left("a", 9223372036854775807)"#),
+        "repeat result too long, limit = 1073741824 bytes",
+    );
+    assert_eq_error(
+        evaluate(r#"pad_right("a", 1073741826, "é")"#),
+        "repeat result too long, limit = 1073741824 bytes",
+    );
+    assert_eq_error(evaluate("upper(1)"), "argument to upper must be String, got = Integer(1)");
+
+    assert_eq_string(evaluate(r#""héllo"[1]"#), "é");
+    assert_eq_error(evaluate(r#""abc"[3]"#), "invalid index: 3");
+    assert_eq_string(evaluate(r#""héllo"[1:3]"#), "él");
+    assert_eq_string(evaluate(r#""héllo"[2:]"#), "llo");
+    assert_eq_string(evaluate(r#""héllo"[:2]"#), "hé");
+    assert_eq_string(evaluate(r#""abc"[:]"#), "abc");
+    assert_eq_string(evaluate(r#""abc"[2:10]"#), "c");
+    assert_eq_string(evaluate(r#""abc"[2:1]"#), "");
+    assert_eq_display(evaluate("[1, 2, 3, 4][1:3]"), "[2, 3]");
+    assert_eq_error(evaluate(r#""abc"["a":]"#), "slice index must be Integer, got = StringValue(\"a\")");
+}
+
+#[test]
+fn test_hash_builtins() {
+    let ab = r#"let h = {"a": 1, "b": 2};"#;
+    let eval_ab = |input: &str| evaluate(&format!("{} {}", ab, input));
+    assert_eq_display(eval_ab("sort(keys(h))"), "[a, b]");
+    assert_eq_display(eval_ab("sort(values(h))"), "[1, 2]");
+    assert_eq_display(
+        eval_ab("sort_by(entries(h), fn(x, y) { x[1] - y[1] })"),
+        "[[a, 1], [b, 2]]",
+    );
+    assert_eq_display(evaluate("keys({1: true})"), "[1]");
+    assert_eq_display(evaluate("keys({true: 1})"), "[true]");
+    assert_eq_bool(eval_ab(r#"has_key(h, "b")"#), true);
+    assert_eq_bool(eval_ab(r#"h.has_key("c")"#), false);
+    assert_eq_int(eval_ab(r#"merge(h, {"a": 10})["a"]"#), 10);
+    assert_eq_display(eval_ab(r#"remove(h, "a")"#), "{b: 2}");
+    assert_eq_int(eval_ab(r#"let r = remove(h, "a"); h["a"]"#), 1);
+    assert_eq_display(eval_ab(r#"remove(h, "c") == h"#), "true");
+    assert_eq_error(
+        evaluate(r#"has_key({"a": 1}, null)"#),
+        "argument to has_key must be hashable, got = Null",
+    );
+    assert_eq_error(evaluate("keys([1])"), "argument to keys must be Hash, got = Array([Integer(1)])");
+
+    assert_eq_string(evaluate(r#"{[1, 2]: "pair"}[[1, 2]]"#), "pair");
+    assert_eq_null(evaluate(r#"{[1, 2]: "pair"}[[2, 1]]"#));
+    assert_eq_string(evaluate(r#"{[1, ["a", true]]: "nested"}[[1, ["a", true]]]"#), "nested");
+    assert_eq_display(evaluate(r#"keys({[1, 2]: "pair"})"#), "[[1, 2]]");
+    assert_eq_null(evaluate(r#"{1: "int"}["1"]"#));
+    assert_eq_bool(evaluate("[1, [2]] == [1, [2]]"), true);
+}
+
+#[test]
+fn test_hash_literal() {
+    assert_eq_display(evaluate(r#"{"a": 1, "b": 2, "c": 3}"#), "{a: 1, b: 2, c: 3}");
+    assert_eq_int(evaluate(r#"{"a": 1, "b": 2}["b"]"#), 2);
+    assert_eq_int(evaluate(r#"let k = "x"; {k: 1, "y" + "z": 2}["yz"]"#), 2);
+    assert_eq_int(evaluate(r#"len(keys({1: "a", true: "b", "1": "c", [1]: "d"}))"#), 4);
+    assert_eq_display(evaluate("{}"), "{}");
+    assert_eq_error(
+        evaluate(r#"{"a": 1, fn(x) { x }: 2}"#),
+        "unusable as hash key: Function",
+    );
+    assert_eq_error(evaluate(r#"{{"a": 1}: 2}"#), "unusable as hash key: Hash");
+    assert_eq_error(evaluate(r#"{"a": 1}[[fn() { 1 }]]"#), "unusable as hash key: Array");
+    assert_eq_error(evaluate(r#"{"a": unwrap(err(1)), "b": 2}"#), "called unwrap on err: 1");
+    assert_eq_display(
+        evaluate(r#"try { {"a": 1}[null] } catch (e) { e.kind }"#),
+        "TypeError",
+    );
+
+    assert_eq_display(evaluate(r#"{"a": 1, "b": 2, "a": 3}"#), "{a: 3, b: 2}");
+    assert_eq_display(evaluate(r#"{[1]: 1, [1]: 2}"#), "{[1]: 2}");
+    let mut env = Environment::new();
+    env.set_duplicate_key_policy(DuplicateKeyPolicy::Error);
+    assert_eq_error(
+        evaluate_in(r#"{"a": 1, "b": 2, "a": 3}"#, &mut env),
+        "duplicate key in hash literal: a",
+    );
+    assert_eq_error(
+        evaluate_in(r#"let f = fn() { {[1]: 1, [1]: 2} }; f()"#, &mut env),
+        "duplicate key in hash literal: [1]",
+    );
+    assert_eq_display(evaluate_in(r#"merge({"a": 1}, {"a": 2})"#, &mut env), "{a: 2}");
+    assert_eq_display(evaluate(r#"{"a": 1, "a": 2}"#), "{a: 2}");
+}
+
+#[test]
+fn test_set() {
+    assert_eq_display(evaluate("#{1, 2, 3}"), "#{1, 2, 3}");
+    assert_eq_display(evaluate("#{3, 1, 3, 2, 1}"), "#{3, 1, 2}");
+    assert_eq_display(evaluate("#{}"), "#{}");
+    assert_eq_display(evaluate(r#"#{[1, 2], "a", true}"#), "#{[1, 2], a, true}");
+    assert_eq_error(evaluate("#{1, fn(x) { x }}"), "unusable as hash key: Function");
+    assert_eq_display(evaluate("set([1, 2, 1, 3, 2])"), "#{1, 2, 3}");
+    assert_eq_int(evaluate("len(#{1, 2, 2})"), 2);
+    assert_eq_display(evaluate("add(#{1, 2}, 3)"), "#{1, 2, 3}");
+    assert_eq_display(evaluate("#{1, 2}.add(1)"), "#{1, 2}");
+    assert_eq_display(evaluate("remove(#{1, 2, 3}, 2)"), "#{1, 3}");
+    assert_eq_display(evaluate("let s = #{1, 2}; let t = remove(s, 1); s"), "#{1, 2}");
+    assert_eq_bool(evaluate("contains(#{1, 2}, 2)"), true);
+    assert_eq_bool(evaluate("contains(#{1, 2}, [2])"), false);
+    assert_eq_bool(evaluate("contains(#{[1, 2]}, [1, 2])"), true);
+    assert_eq_display(evaluate("union(#{1, 2}, #{2, 3})"), "#{1, 2, 3}");
+    assert_eq_display(evaluate("intersection(#{1, 2, 3}, #{3, 2, 4})"), "#{2, 3}");
+    assert_eq_display(evaluate("difference(#{1, 2, 3}, #{2})"), "#{1, 3}");
+    assert_eq_error(evaluate("union(#{1}, [2])"), "argument to union must be Set, got = Array([Integer(2)])");
+
+    assert_eq_bool(evaluate("#{1, 2, 3} == #{3, 2, 1}"), true);
+    assert_eq_bool(evaluate("#{1, 2} == #{1, 2, 3}"), false);
+    assert_eq_bool(evaluate("#{1} != #{2}"), true);
+
+    assert_eq_display(evaluate("map(#{1, 2, 3}, fn(x) { x * 10 })"), "[10, 20, 30]");
+    assert_eq_int(evaluate("reduce(#{1, 2, 3}, 0, fn(a, b) { a + b })"), 6);
+    assert_eq_display(evaluate("sort(#{3, 1, 2})"), "[1, 2, 3]");
+    assert_eq_display(evaluate("[3, 1, 3, 2].set().sort()"), "[1, 2, 3]");
+}
+
+#[test]
+fn test_json() {
+    // Monkey strings have no escapes, so JSON text containing quotes comes from the host.
+    let mut env = Environment::new();
+    let json = |env: &mut Environment, name: &str, text: &str| {
+        env.set(name.to_owned(), Rc::new(Object::StringValue(text.to_owned())));
+    };
+    json(&mut env, "doc", r#" {"a": [1, -2, true, null], "b": {"c": "d"}, "e": [], "f": {}} "#);
+    assert_eq_display(
+        evaluate_in("json_parse(doc)", &mut env),
+        "{a: [1, -2, true, null], b: {c: d}, e: [], f: {}}",
+    );
+    assert_eq_int(evaluate_in("json_parse(doc).a[1]", &mut env), -2);
+    assert_eq_string(evaluate_in("json_parse(doc).b.c", &mut env), "d");
+    assert_eq_string(
+        evaluate_in("json_stringify(json_parse(doc))", &mut env),
+        r#"{"a":[1,-2,true,null],"b":{"c":"d"},"e":[],"f":{}}"#,
+    );
+    assert_eq_string(
+        evaluate_in("json_stringify(json_parse(doc), 2)", &mut env),
+        "{\n  \"a\": [\n    1,\n    -2,\n    true,\n    null\n  ],\n  \"b\": {\n    \"c\": \"d\"\n  },\n  \"e\": [],\n  \"f\": {}\n}",
+    );
+    assert_eq_bool(
+        evaluate_in("json_parse(json_stringify(json_parse(doc), 4)) == json_parse(doc)", &mut env),
+        true,
+    );
+
+    json(&mut env, "escaped", r#""q\" b\\ n\n t\t ué 😀 s\/""#);
+    assert_eq_string(
+        evaluate_in("json_parse(escaped)", &mut env),
+        "q\" b\\ n\n t\t u\u{e9} \u{1f600} s/",
+    );
+    assert_eq_string(
+        evaluate_in("json_stringify(json_parse(escaped))", &mut env),
+        r#""q\" b\\ n\n t\t ué 😀 s/""#,
+    );
+    json(&mut env, "control", "\u{1}");
+    assert_eq_string(evaluate_in("json_stringify(control)", &mut env), r#""\u0001""#);
+
+    assert_eq_string(evaluate("json_stringify(#{1, 2})"), "[1,2]");
+    assert_eq_string(evaluate("json_stringify(null)"), "null");
+    assert_eq_string(
+        evaluate("struct Point { x, y } json_stringify(Point(1, [2]))"),
+        r#"{"x":1,"y":[2]}"#,
+    );
+    assert_eq_error(evaluate("json_stringify({1: 2})"), "JSON object keys must be String, got = Integer");
+    assert_eq_error(evaluate(r#"json_stringify({"f": fn(x) { x }})"#), "cannot convert Function to JSON");
+    assert_eq_error(evaluate("json_stringify([len])"), "cannot convert Builtin to JSON");
+    assert_eq_error(evaluate("json_stringify(1, -1)"), "indent must not be negative, got = -1");
+    assert_eq_error(evaluate("json_parse(1)"), "argument to json_parse must be String, got = Integer(1)");
+
+    json(&mut env, "unclosed", "{\"a\": [1,\n 2");
+    assert_eq_error(
+        evaluate_in("json_parse(unclosed)", &mut env),
+        "invalid JSON: unexpected end of input at line 2, column 3",
+    );
+    json(&mut env, "float", "[1.5]");
+    assert_eq_error(
+        evaluate_in("json_parse(float)", &mut env),
+        "invalid JSON: only integer numbers are supported at line 1, column 2",
+    );
+    json(&mut env, "key", "{1: 2}");
+    assert_eq_error(
+        evaluate_in("json_parse(key)", &mut env),
+        "invalid JSON: expected '\"', got '1' at line 1, column 2",
+    );
+    assert_eq_error(evaluate(r#"json_parse("[1] x")"#), "invalid JSON: unexpected trailing characters at line 1, column 5");
+    json(&mut env, "nested", &format!("{}{}", "[".repeat(512), "]".repeat(512)));
+    assert_eq_int(evaluate_in("len(json_parse(nested))", &mut env), 1);
+    json(&mut env, "deep", &"[".repeat(20000));
+    assert_eq_error(
+        evaluate_in("json_parse(deep)", &mut env),
+        "invalid JSON: nesting too deep at line 1, column 513",
+    );
+    json(&mut env, "deep_object", &r#"{"a":"#.repeat(20000));
+    assert_eq_error(
+        evaluate_in("json_parse(deep_object)", &mut env),
+        "invalid JSON: nesting too deep at line 1, column 2561",
+    );
+    assert_eq_error(evaluate(r#"json_parse("tru")"#), "invalid JSON: expected true at line 1, column 1");
+    assert_eq_error(evaluate(r#"json_parse("")"#), "invalid JSON: unexpected end of input at line 1, column 1");
+}
+
+#[test]
+fn test_negative_index_and_slice() {
+    assert_eq_int(evaluate("[1, 2, 3][-1]"), 3);
+    assert_eq_int(evaluate("[1, 2, 3][-3]"), 1);
+    assert_eq_error(evaluate("[1, 2, 3][-4]"), "invalid index: -4");
+    assert_eq_error(evaluate("[1, 2, 3][3]"), "invalid index: 3");
+    assert_eq_error(evaluate("[][0]"), "invalid index: 0");
+    assert_eq_error(evaluate("[][-1]"), "invalid index: -1");
+    assert_eq_string(evaluate(r#""héllo"[-4]"#), "é");
+    assert_eq_error(evaluate(r#""abc"[-4]"#), "invalid index: -4");
+    assert_eq_string(evaluate(r#"try { [][0] } catch (e) { e.kind }"#), "IndexError");
+
+    assert_eq_display(evaluate("[1, 2, 3, 4][1:3]"), "[2, 3]");
+    assert_eq_display(evaluate("[1, 2, 3, 4][:-1]"), "[1, 2, 3]");
+    assert_eq_display(evaluate("[1, 2, 3, 4][-2:]"), "[3, 4]");
+    assert_eq_display(evaluate("[1, 2, 3, 4][-10:2]"), "[1, 2]");
+    assert_eq_display(evaluate("[1, 2, 3, 4][3:1]"), "[]");
+    assert_eq_display(evaluate("[][:]"), "[]");
+    assert_eq_display(evaluate("let a = [1, 2, 3]; let b = a[1:]; a"), "[1, 2, 3]");
+    assert_eq_string(evaluate(r#""héllo"[2:]"#), "llo");
+    assert_eq_string(evaluate(r#""héllo"[:-3]"#), "hé");
+    assert_eq_string(evaluate(r#""héllo"[-3:-1]"#), "ll");
+    assert_eq_error(evaluate("1[1:2]"), "slice operator not supported: Integer(1)");
+}
+
+// Hashes keep their keys in the order they were first inserted: printing, keys,
+// values, entries and equality all follow it.
+#[test]
+fn test_hash_ordering() {
+    let hash = r#"let h = {"b": 1, "a": 2, "c": 3};"#;
+    let eval_hash = |input: &str| evaluate(&format!("{} {}", hash, input));
+    assert_eq_display(eval_hash("h"), "{b: 1, a: 2, c: 3}");
+    assert_eq_display(eval_hash("keys(h)"), "[b, a, c]");
+    assert_eq_display(eval_hash("values(h)"), "[1, 2, 3]");
+    assert_eq_display(eval_hash("entries(h)"), "[[b, 1], [a, 2], [c, 3]]");
+    assert_eq_display(eval_hash(r#"merge(h, {"b": 10})"#), "{b: 10, a: 2, c: 3}");
+    assert_eq_display(eval_hash(r#"merge(remove(h, "b"), {"b": 1})"#), "{a: 2, c: 3, b: 1}");
+    assert_eq_bool(eval_hash(r#"h == {"b": 1, "a": 2, "c": 3}"#), true);
+    assert_eq_bool(eval_hash(r#"h == {"c": 3, "a": 2, "b": 1}"#), false);
+    assert_eq_bool(eval_hash(r#"h == {"b": 1, "a": 2, "c": 4}"#), false);
+    assert_eq_bool(eval_hash(r#"h == {"b": 1, "a": 2, "d": 3}"#), false);
+    assert_eq_bool(eval_hash(r#"h == {"b": 1, "a": 2}"#), false);
+    assert_eq_bool(evaluate(r#"{"a": 1, "b": 2} == {"b": 2, "a": 1}"#), false);
+    assert_eq_bool(evaluate(r#"{"a": {"x": 1, "y": 2}} == {"a": {"y": 2, "x": 1}}"#), false);
+    assert_eq_display(
+        evaluate("try { throw 1; } catch (e) { keys(e) }"),
+        "[message, kind, stack, value]",
+    );
+}
+
+fn evaluate_with_prelude(input: &str) -> Option<Rc<Object>> {
+    let mut lexer = Lexer::new(input.to_string());
+    let mut parser = Parser::new(lexer);
+    let program = parser.parse_program().expect("failed to parse program");
+    let mut env = Environment::new_with_prelude();
+    let object = eval(&program, &mut env);
+    println!("OBJECT: {:?}", object);
+    return object;
+}
+
+#[test]
+fn test_register_fn() {
+    let mut env = Environment::new();
+    env.register_fn("http_status", |args: &[Value]| -> Result<Value, native::Error> {
+        native::expect_arity(args, 1)?;
+        let text = match native::int_arg(args, 0)? {
+            200 => "OK",
+            404 => "Not Found",
+            code => return Err(format!("unknown status: {}", code).into()),
+        };
+        Ok(Rc::new(Object::StringValue(text.to_owned())))
+    });
+    let calls = Rc::new(Cell::new(0));
+    let counter = calls.clone();
+    env.register_fn("len", move |args: &[Value]| {
+        counter.set(counter.get() + 1);
+        let s = native::str_arg(args, 0)?;
+        Ok(Rc::new(Object::Integer(s.len() as i64 * 100)))
+    });
+    env.remove_fn("first");
+
+    assert_eq_string(evaluate_in("http_status(404)", &mut env), "Not Found");
+    assert_eq_string(evaluate_in("[200].map(http_status)[0]", &mut env), "OK");
+    assert_eq_error(evaluate_in("http_status(500)", &mut env), "unknown status: 500");
+    assert_eq_error(
+        evaluate_in(r#"http_status("a")"#, &mut env),
+        "argument to http_status must be Integer, got = StringValue(\"a\")",
+    );
+    assert_eq_string(
+        evaluate_in(r#"try { http_status("a") } catch (e) { e.kind }"#, &mut env),
+        "ArgumentError",
+    );
+    assert_eq_error(
+        evaluate_in("http_status()", &mut env),
+        "wrong number of arguments, got = 0, want = 1",
+    );
+    assert_eq_string(
+        evaluate_in("try { http_status(1) } catch (e) { e.message }", &mut env),
+        "unknown status: 1",
+    );
+    assert_eq_display(evaluate_in("http_status", &mut env), "builtin http_status");
+
+    assert_eq_int(evaluate_in(r#"len("ab")"#, &mut env), 200);
+    assert_eq_int(evaluate_in(r#""abc".len()"#, &mut env), 300);
+    assert_eq!(calls.get(), 2);
+    assert_eq_error(evaluate_in("first([1])", &mut env), "identifier not found: \"first\"");
+    assert_eq_error(evaluate_in("[1].first()", &mut env), "unknown method: first");
+    assert_eq_int(evaluate_in("last([1])", &mut env), 1);
+    assert_eq_int(evaluate_in("let http_status = fn(x) { x }; http_status(1)", &mut env), 1);
+
+    env.register_fn("first", |args: &[Value]| Ok(native::array_arg(args, 0)?[0].clone()));
+    assert_eq_int(evaluate_in("first([7])", &mut env), 7);
+
+    let dir = module_dir("register_fn");
+    write_module(&dir, "status.monkey", "export let ok = http_status(200);");
+    write_module(&dir, "main.monkey", r#"import "status.monkey" as s; s.ok"#);
+    assert_eq_string(eval_file(&dir.join("main.monkey"), &mut env), "OK");
+}
+
+fn evaluate_in(input: &str, env: &mut Environment) -> Option<Rc<Object>> {
+    let mut parser = Parser::new(Lexer::new(input.to_string()));
+    let program = parser.parse_program().expect("failed to parse program");
+    let object = eval(&program, env);
+    println!("OBJECT: {:?}", object);
+    return object;
+}
+
+fn module_dir(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("rust_monkey_{}_{}", name, process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    return dir;
+}
+
+fn write_module(dir: &Path, name: &str, source: &str) {
+    let path = dir.join(name);
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, source).unwrap();
+}
+
+fn evaluate_file(path: &Path) -> Option<Rc<Object>> {
+    let mut env = Environment::new();
+    let object = eval_file(path, &mut env);
+    println!("OBJECT: {:?}", object);
+    return object;
+}
+
 fn evaluate(input: &str) -> Option<Rc<Object>> {
     let mut lexer = Lexer::new(input.to_string());
     let mut parser = Parser::new(lexer);
@@ -109,3 +1013,11 @@ fn assert_eq_error(result: Option<Rc<Object>>, s: &str) {
 fn assert_eq_string(result: Option<Rc<Object>>, s: &str) {
     assert_eq!(result, Some(Rc::new(Object::StringValue(s.to_owned()))));
 }
+
+fn assert_eq_null(result: Option<Rc<Object>>) {
+    assert_eq!(result, Some(Rc::new(Object::Null)));
+}
+
+fn assert_eq_display(result: Option<Rc<Object>>, s: &str) {
+    assert_eq!(result.map(|o| o.to_string()), Some(s.to_owned()));
+}
@@ -0,0 +1,183 @@
+extern crate rust_monkey;
+
+use rust_monkey::environment::DuplicateKeyPolicy;
+use rust_monkey::interpreter::{Interpreter, MonkeyError};
+use rust_monkey::native;
+use rust_monkey::object::{Object, Value};
+
+use std::cell::RefCell;
+use std::env;
+use std::fs;
+use std::process;
+use std::rc::{Rc, Weak};
+
+#[test]
+fn test_eval_str() {
+    let mut interpreter = Interpreter::new();
+    assert_eq!(*interpreter.eval_str("1 + 2").unwrap(), Object::Integer(3));
+    assert_eq!(*interpreter.eval_str("let x = 5;").unwrap(), Object::Integer(5));
+    assert_eq!(*interpreter.eval_str("x * 2").unwrap(), Object::Integer(10));
+    assert_eq!(*interpreter.eval_str("").unwrap(), Object::Null);
+    assert_eq!(*interpreter.eval_str("return 7; 8").unwrap(), Object::Integer(7));
+
+    assert_eq!(
+        interpreter.eval_str("let a = 1 # 2;"),
+        Err(MonkeyError::Lex(vec!["illegal token: \"#\"".to_owned()]))
+    );
+    match interpreter.eval_str("let = 1;") {
+        Err(MonkeyError::Parse(errors)) => assert!(!errors.is_empty()),
+        result => panic!("expected a parse error, got {:?}", result),
+    }
+    assert_eq!(
+        interpreter.eval_str("export 1;"),
+        Err(MonkeyError::Parse(vec![
+            "expected let or fn after export, got Int(\"1\")".to_owned()
+        ]))
+    );
+    match interpreter.eval_str("export struct P { x }") {
+        Err(MonkeyError::Parse(errors)) => {
+            assert_eq!(errors[0], "expected let or fn after export, got Struct")
+        }
+        result => panic!("expected a parse error, got {:?}", result),
+    }
+    assert_eq!(
+        interpreter.eval_str("1 + true"),
+        Err(MonkeyError::Runtime("type mismatch: Integer(1), \"+\", Bool(true)".to_owned()))
+    );
+    assert_eq!(
+        interpreter.eval_str(r#"throw "boom";"#),
+        Err(MonkeyError::Thrown(Rc::new(Object::StringValue("boom".to_owned()))))
+    );
+    assert_eq!(
+        interpreter.eval_str("missing").unwrap_err().to_string(),
+        "identifier not found: \"missing\""
+    );
+    assert_eq!(
+        interpreter.eval_str("x(1)"),
+        Err(MonkeyError::Runtime("not a function: Integer".to_owned()))
+    );
+    assert_eq!(*interpreter.eval_str("fn() {}()").unwrap(), Object::Null);
+
+    interpreter.set_duplicate_key_policy(DuplicateKeyPolicy::Error);
+    assert_eq!(
+        interpreter.eval_str(r#"{"a": 1, "a": 2}"#),
+        Err(MonkeyError::Runtime("duplicate key in hash literal: a".to_owned()))
+    );
+
+    let mut interpreter = Interpreter::new_with_prelude();
+    assert_eq!(
+        interpreter.eval_str("map([1, 2, 3], fn(x) { x * 2 })").unwrap().to_string(),
+        "[2, 4, 6]"
+    );
+    assert_eq!(interpreter.eval_str("map").unwrap().to_string(), "builtin map");
+}
+
+#[test]
+fn test_eval_file() {
+    let dir = env::temp_dir().join(format!("rust_monkey_interpreter_{}", process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("lib.monkey"), "export fn twice(x) { x * 2 }").unwrap();
+    fs::write(dir.join("main.monkey"), r#"import "lib.monkey" as lib; lib.twice(21)"#).unwrap();
+    fs::write(dir.join("broken.monkey"), "let = 1;").unwrap();
+
+    let mut interpreter = Interpreter::new();
+    assert_eq!(
+        *interpreter.eval_file(&dir.join("main.monkey")).unwrap(),
+        Object::Integer(42)
+    );
+    assert!(interpreter.get_global("lib").is_some());
+    match interpreter.eval_file(&dir.join("broken.monkey")) {
+        Err(MonkeyError::Parse(_)) => {}
+        result => panic!("expected a parse error, got {:?}", result),
+    }
+    match interpreter.eval_file(&dir.join("missing.monkey")) {
+        Err(MonkeyError::Io(message)) => assert!(message.starts_with("cannot read")),
+        result => panic!("expected an io error, got {:?}", result),
+    }
+}
+
+#[test]
+fn test_modules_are_per_interpreter() {
+    let dir = env::temp_dir().join(format!("rust_monkey_interpreter_modules_{}", process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("greeting.monkey"), "export let text = host_name();").unwrap();
+    fs::write(dir.join("main.monkey"), r#"import "greeting.monkey" as g; g.text"#).unwrap();
+
+    let mut first = Interpreter::new();
+    first.register_fn("host_name", |_: &[Value]| Ok(Rc::new(Object::StringValue("one".to_owned()))));
+    let mut second = Interpreter::new();
+    second.register_fn("host_name", |_: &[Value]| Ok(Rc::new(Object::StringValue("two".to_owned()))));
+
+    let main = dir.join("main.monkey");
+    assert_eq!(first.eval_file(&main).unwrap().to_string(), "one");
+    assert_eq!(second.eval_file(&main).unwrap().to_string(), "two");
+}
+
+#[test]
+fn test_call_function_and_globals() {
+    let mut interpreter = Interpreter::new();
+    interpreter
+        .eval_str("fn add(a, b) { a + b } struct Point { x, y } let n = 1;")
+        .unwrap();
+
+    let args = vec![Rc::new(Object::Integer(2)), Rc::new(Object::Integer(3))];
+    assert_eq!(*interpreter.call_function("add", &args).unwrap(), Object::Integer(5));
+    assert_eq!(
+        interpreter.call_function("Point", &args).unwrap().to_string(),
+        "Point { x: 2, y: 3 }"
+    );
+    assert_eq!(
+        *interpreter
+            .call_function("len", &[Rc::new(Object::StringValue("abc".to_owned()))])
+            .unwrap(),
+        Object::Integer(3)
+    );
+    assert_eq!(
+        interpreter.call_function("n", &[]),
+        Err(MonkeyError::Runtime("not a function: Integer".to_owned()))
+    );
+    assert_eq!(
+        interpreter.call_function("nope", &[]),
+        Err(MonkeyError::Runtime("identifier not found: \"nope\"".to_owned()))
+    );
+
+    assert_eq!(*interpreter.get_global("n").unwrap(), Object::Integer(1));
+    assert!(interpreter.get_global("len").is_none());
+    interpreter.set_global("n", Rc::new(Object::Integer(41)));
+    assert_eq!(*interpreter.eval_str("n + 1").unwrap(), Object::Integer(42));
+
+    interpreter.register_fn("negate", |args: &[Value]| {
+        Ok(Rc::new(Object::Integer(-native::int_arg(args, 0)?)))
+    });
+    assert_eq!(*interpreter.eval_str("negate(n)").unwrap(), Object::Integer(-41));
+    interpreter.remove_fn("negate");
+    assert!(interpreter.eval_str("negate(n)").is_err());
+}
+
+#[test]
+fn test_drop_frees_closures() {
+    let tracked = Rc::new(RefCell::new(Vec::<Weak<Object>>::new()));
+    let mut interpreter = Interpreter::new();
+    let track = tracked.clone();
+    interpreter.register_fn("track", move |args: &[Value]| {
+        track.borrow_mut().push(Rc::downgrade(&args[0]));
+        Ok(args[0].clone())
+    });
+
+    // Each of these scopes holds a function that holds the scope.
+    interpreter
+        .eval_str(
+            "fn make() { let data = track([1]); fn get() { data } get }
+             let g = make();
+             fn run() { let data = track([2]); let get = fn() { data }; get() }
+             run();
+             match (1) { n => { let data = track([3]); fn get() { data } get() } }
+             let h = track([4]);",
+        )
+        .unwrap();
+    assert_eq!(interpreter.eval_str("g()").unwrap().to_string(), "[1]");
+    assert_eq!(tracked.borrow().len(), 4);
+
+    drop(interpreter);
+    assert!(tracked.borrow().iter().all(|value| value.upgrade().is_none()));
+}
@@ -28,6 +28,21 @@ fn test_parser() {
     parse(r#"a[0]"#);
     parse(r#"[1, 2, 3][0]"#);
     parse(r#"{"one":1, true:2, 3:false}"#);
+    parse("fn add(x, y) { x + y; }");
+    parse(r#"config.name"#);
+    parse(r#"arr.push(4).len()"#);
+    parse(r#"xs |> filter(f) |> map(g) |> sum()"#);
+    parse(r#"config?.db?.[0] ?? null"#);
+    parse(r#"try { throw "x"; } catch (e) { e.message } finally { 1 }"#);
+    parse(r#"let v = parse(s)?.value;"#);
+    parse(r#"struct Point { x, y } Point { x: 1, y: 2 }.x"#);
+    parse(r#"enum Shape { Circle(r), Rect(w, h), Empty }"#);
+    parse("a[1:2]; a[:2]; a[1:]; a[:]");
+    parse("#{1, 2, 3}; #{}");
+    parse(r#"import "lib/math.monkey" as math;"#);
+    parse("export let x = 1; export fn f(a) { a }");
+    parse("impl Point { fn __add__(a, b) { a } fn norm(p) { p.x } }");
+    parse(r#"match (s) { Circle(r) => r * r, Empty => { 0 }, _ => 1 }"#);
 }
 
 fn parse(input: &str) {
@@ -0,0 +1,156 @@
+#![cfg(feature = "serde")]
+
+extern crate rust_monkey;
+
+use rust_monkey::interpreter::Interpreter;
+use rust_monkey::object::{Object, Value};
+use rust_monkey::serde_value::{from_value, to_value};
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::rc::Rc;
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Config {
+    name: String,
+    retries: u32,
+    verbose: bool,
+    tags: Vec<String>,
+    limits: BTreeMap<String, i64>,
+    proxy: Option<String>,
+    mode: Mode,
+    shapes: Vec<Shape>,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+enum Mode {
+    Fast,
+    Safe,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+enum Shape {
+    Circle(u32),
+    Rect { w: u32, h: u32 },
+    Line(i32, i32),
+}
+
+fn config() -> Config {
+    let mut limits = BTreeMap::new();
+    limits.insert("cpu".to_owned(), 2);
+    limits.insert("mem".to_owned(), 512);
+    Config {
+        name: "worker".to_owned(),
+        retries: 3,
+        verbose: false,
+        tags: vec!["a".to_owned(), "b".to_owned()],
+        limits,
+        proxy: None,
+        mode: Mode::Safe,
+        shapes: vec![
+            Shape::Circle(1),
+            Shape::Rect { w: 2, h: 3 },
+            Shape::Line(-1, 1),
+        ],
+    }
+}
+
+#[test]
+fn test_to_and_from_value() {
+    let value = to_value(&config()).unwrap();
+    assert_eq!(
+        value.to_string(),
+        "{name: worker, retries: 3, verbose: false, tags: [a, b], limits: {cpu: 2, mem: 512}, \
+         proxy: null, mode: Safe, shapes: [{Circle: 1}, {Rect: {w: 2, h: 3}}, {Line: [-1, 1]}]}"
+    );
+    assert_eq!(from_value::<Config>(&value).unwrap(), config());
+
+    assert_eq!(to_value(&1.5).unwrap_err().to_string(), "floating point numbers are not supported, got = 1.5");
+    assert_eq!(to_value(&u64::MAX).unwrap_err().to_string(), "integer out of range: 18446744073709551615");
+    assert_eq!(
+        from_value::<u8>(&Rc::new(Object::Integer(300))).unwrap_err().to_string(),
+        "invalid value: integer `300`, expected u8"
+    );
+}
+
+#[test]
+fn test_interpreter_globals() {
+    let mut interpreter = Interpreter::new();
+    interpreter.set_global("config", to_value(&config()).unwrap());
+    let result = interpreter
+        .eval_str(
+            r#"
+            let c = config;
+            { "name": upper(c.name), "retries": c.retries + 1, "verbose": !c.verbose,
+              "tags": push(c.tags, "c"), "limits": merge(c.limits, {"io": 1}), "proxy": "p",
+              "mode": "Fast", "shapes": rest(c.shapes) }
+            "#,
+        )
+        .unwrap();
+    let updated = from_value::<Config>(&result).unwrap();
+    assert_eq!(updated.name, "WORKER");
+    assert_eq!(updated.retries, 4);
+    assert!(updated.verbose);
+    assert_eq!(updated.tags, vec!["a", "b", "c"]);
+    assert_eq!(updated.limits["io"], 1);
+    assert_eq!(updated.proxy, Some("p".to_owned()));
+    assert_eq!(updated.mode, Mode::Fast);
+    assert_eq!(updated.shapes, vec![Shape::Rect { w: 2, h: 3 }, Shape::Line(-1, 1)]);
+
+    let error = from_value::<Config>(&interpreter.eval_str(r#"{"name": 1}"#).unwrap()).unwrap_err();
+    assert_eq!(error.to_string(), "invalid type: integer `1`, expected a string");
+}
+
+#[test]
+fn test_monkey_structs_and_enums() {
+    let mut interpreter = Interpreter::new();
+    let value = interpreter
+        .eval_str(
+            r#"
+            struct Size { w, h }
+            enum Shape { Circle(r), Rect(w, h), Line(a, b) }
+            [Circle(4), Rect(2, 3), Line(1, 2)]
+            "#,
+        )
+        .unwrap();
+    assert_eq!(
+        from_value::<Vec<Shape>>(&value).unwrap(),
+        vec![Shape::Circle(4), Shape::Rect { w: 2, h: 3 }, Shape::Line(1, 2)]
+    );
+    assert_eq!(value.to_string(), "[Circle(4), Rect(2, 3), Line(1, 2)]");
+    assert_eq!(
+        serde_json::to_string(&value).unwrap(),
+        r#"[{"Circle":4},{"Rect":[2,3]},{"Line":[1,2]}]"#
+    );
+    let value = interpreter.eval_str("Size(5, 6)").unwrap();
+    assert_eq!(from_value::<BTreeMap<String, u32>>(&value).unwrap()["h"], 6);
+}
+
+#[test]
+fn test_serialize_object() {
+    let mut interpreter = Interpreter::new();
+    let value = interpreter
+        .eval_str(r#"{"a": [1, true, null], "b": #{"x"}, "c": {"d": "e"}}"#)
+        .unwrap();
+    let json = serde_json::to_string(&value).unwrap();
+    assert_eq!(json, r#"{"a":[1,true,null],"b":["x"],"c":{"d":"e"}}"#);
+
+    let parsed: Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(parsed.to_string(), r#"{a: [1, true, null], b: [x], c: {d: e}}"#);
+    interpreter.set_global("parsed", parsed);
+    assert_eq!(*interpreter.eval_str("parsed.c.d").unwrap(), Object::StringValue("e".to_owned()));
+
+    let function = interpreter.eval_str("fn(x) { x }").unwrap();
+    assert_eq!(
+        serde_json::to_string(&function).unwrap_err().to_string(),
+        "cannot serialize Function"
+    );
+    assert_eq!(
+        serde_json::from_str::<Value>("[1.5]").unwrap_err().to_string(),
+        "floating point numbers are not supported, got = 1.5 at line 1 column 4"
+    );
+    assert_eq!(
+        serde_json::from_str::<Value>("18446744073709551615").unwrap_err().to_string(),
+        "integer out of range: 18446744073709551615 at line 1 column 20"
+    );
+}